@@ -0,0 +1,40 @@
+// The functions a ZINNIA plugin has to export.
+//
+// Build a shared library (.so on Linux) that exports these and put it in the
+// plugins directory next to resources. ZINNIA loads it at startup and adds it
+// to the list of commands. All strings are UTF-8 and null terminated.
+//
+// Each plugin runs in its own process, so if it panics, crashes, or takes too
+// long to answer, only that process ends and ZINNIA turns the plugin off.
+// Anything the plugin prints to stdout is passed through to ZINNIA's output.
+// zinnia_plugin_recognize should answer quickly, since it's asked about
+// everything that's heard. One that takes more than half a second gets the
+// plugin turned off.
+
+#ifndef ZINNIA_PLUGIN_H
+#define ZINNIA_PLUGIN_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+// must return ZINNIA_PLUGIN_ABI_VERSION, plugins for other versions are not loaded
+#define ZINNIA_PLUGIN_ABI_VERSION 1
+uint32_t zinnia_plugin_abi_version(void);
+
+// information about the command, these are copied once when the plugin is loaded
+// and are never freed, so returning string literals is fine
+const char *zinnia_plugin_name(void);
+const char *zinnia_plugin_desc(void);
+const char *zinnia_plugin_help(void);
+bool zinnia_plugin_uses_internet(void);
+
+// whether the command should handle what the user said
+bool zinnia_plugin_recognize(const char *text);
+
+// handles what the user said, and returns what ZINNIA should say back
+// (or NULL to say nothing). The returned string is given back to
+// zinnia_plugin_free once ZINNIA has copied it
+char *zinnia_plugin_effect(const char *text);
+void zinnia_plugin_free(char *response);
+
+#endif
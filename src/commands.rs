@@ -1,5 +1,14 @@
-use std::sync::mpsc::{Sender};
+use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::any::Any;
+use std::time::{Duration, Instant};
 use crate::SpeakMessage;
+use crate::config::Config;
+use crate::http::HttpService;
+use crate::storage::Storage;
+use crate::timers::TimerService;
+use crate::workers::WorkerPool;
 
 // all the command modules
 mod test;
@@ -12,22 +21,75 @@ mod joke;
 use joke::JokeCommand;
 mod dice;
 use dice::DiceCommand;
+mod initiative;
+use initiative::InitiativeCommand;
+mod random;
+use random::RandomCommand;
+mod calculator;
+use calculator::CalculatorCommand;
 mod alarm;
 use alarm::AlarmCommand;
+mod reminder;
+use reminder::ReminderCommand;
+mod note;
+use note::NoteCommand;
+mod routine;
+use routine::RoutineCommand;
+mod stopwatch;
+use stopwatch::StopwatchCommand;
+mod clock;
+use clock::ClockCommand;
+mod lists;
+use lists::ListCommand;
+mod custom;
+use custom::CustomCommand;
+mod plugin;
+use plugin::PluginCommand;
+pub use plugin::{HOST_ARG as PLUGIN_HOST_ARG, run_host as run_plugin_host};
+mod shell;
+use shell::ShellCommand;
+// helpers shared between commands
+mod spoken;
+
+// phrases the director handles itself, before any command sees the text
+const CANCEL_PHRASES : [&str; 6] = ["cancel", "cancel that", "never mind", "nevermind", "forget it", "forget about it"];
+const REPEAT_PHRASES : [&str; 7] = ["repeat", "repeat that", "say that again", "what did you say", "come again", "pardon", "sorry what"];
+const OFFLINE_PHRASES : [&str; 5] = ["go offline", "offline mode", "offline mode on", "turn on offline mode", "stay offline"];
+const ONLINE_PHRASES : [&str; 4] = ["go online", "offline mode off", "turn off offline mode", "online mode"];
+
+// where user-defined commands are loaded from
+const CUSTOM_COMMAND_DIR : &str = "custom_commands";
+// where plugin libraries are loaded from
+const PLUGIN_DIR : &str = "plugins";
+// where the programs that can be run by voice are set up
+const SHELL_COMMAND_FILE : &str = "shell_commands.toml";
+
+// how long a command can wait for more input before its conversation is dropped
+const SESSION_TIMEOUT : Duration = Duration::from_secs(30);
+// how long a conversation that takes everything that's said (like dictation) can go without hearing anything before it's ended
+const RAW_SESSION_TIMEOUT : Duration = Duration::from_secs(120);
+// how long a command can run in the background before the user is told it's still going
+const STILL_WORKING_AFTER : Duration = Duration::from_secs(3);
+// how many long running commands can run at the same time
+const WORKER_THREADS : usize = 2;
+
+// the state of a conversation with a command, held by the director between rounds of input
+// each command decides what goes in here and downcasts it back when it's resumed
+pub type SessionState = Box<dyn Any + Send>;
 
 // the result of the execution of a command, indicates whether it needs more input
 // it used to be different from DispatchResult, but has since been changed to be the same.
 // it's being kept separate in case that changes again
 pub enum CommandResult {
     Done,
-    Continue,
+    Continue(SessionState),
 }
 impl CommandResult {
     // converts to DispatchResult, for sending to things that don't need the function'
     pub fn to_dispatch(&self) -> DispatchResult {
         match self {
             CommandResult::Done => {return DispatchResult::Done},
-            CommandResult::Continue => {return DispatchResult::Continue},
+            CommandResult::Continue(_) => {return DispatchResult::Continue},
         }
     }
 }
@@ -36,72 +98,320 @@ impl CommandResult {
 pub enum DispatchResult {
     Done,
     Continue,
+    Working, // the command is running in the background, poll will give the result once it's finished
 }
 
 // Trait to define all the functionality of a Zinnia command
-pub trait Command {
+pub trait Command : Send {
     fn name(&self) -> String;
     fn desc(&self) -> String;
     fn help(&self) -> String;
     fn uses_internet(&self) -> bool;
+    // other names people might use for the command, for looking it up by name
+    fn aliases(&self) -> Vec<String> {
+        return Vec::new();
+    }
+    // example phrases that would use the command
+    fn examples(&self) -> Vec<String> {
+        return Vec::new();
+    }
+    // whether the command might take a while, in which case it's run on a worker thread
+    fn runs_long(&self) -> bool {
+        return self.uses_internet();
+    }
     fn recognize(&self, text : String) -> bool;
+    // begins a conversation with the command
     fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult;
+    // continues a conversation, given the state from the last time the command returned Continue
+    fn resume(&mut self, _state : SessionState, _text : String, _speak : Sender<SpeakMessage>) -> CommandResult {
+        return CommandResult::Done;
+    }
+    // ends a conversation that didn't finish on its own, because it was cancelled or timed out
+    fn end(&mut self, _state : SessionState, _speak : Sender<SpeakMessage>) {}
+    // whether a conversation with the command gets everything that's said exactly as it was said, like dictation
+    // phrases like "cancel" go to the command instead, so it has to end them itself, unless nothing's said for RAW_SESSION_TIMEOUT
+    // or it's stopped from the tray, which both call end
+    fn takes_raw_text(&self) -> bool {
+        return false;
+    }
+}
+
+// a conversation with a command that is waiting on more input
+struct Session {
+    command : usize,
+    state : SessionState,
+    last_active : Instant,
+}
+
+// a command that's running on a worker thread
+struct Pending {
+    command : usize,
+    result : Receiver<CommandResult>,
+    started : Instant,
+    cued : bool, // whether the user has been told it's still working
+}
+
+// a command shared with the worker threads, which lock it while it runs
+type SharedCommand = Arc<Mutex<Box<dyn Command>>>;
+
+// locks a command, even if a panic poisoned it, since a command that panicked can still be run again
+fn lock(command : &SharedCommand) -> MutexGuard<'_, Box<dyn Command>> {
+    return command.lock().unwrap_or_else(|e| e.into_inner());
 }
 
 // the object responsible for running commands
 pub struct CommandDirector {
-    commands : Vec<Box<dyn Command>>,
-    next_comm : Option<usize>,
+    commands : Vec<SharedCommand>,
+    session : Option<Session>,
+    pending : Option<Pending>,
+    workers : WorkerPool,
     speak : Sender<SpeakMessage>,
+    offline : Arc<AtomicBool>, // when set, commands that use the internet are refused
 }
 impl CommandDirector {
     // this should populate the commands list with all available commands, in order of priority
-    pub fn new(speak : Sender<SpeakMessage>) -> CommandDirector {
-        let mut cd = CommandDirector {
-            commands : Vec::new(),
-            next_comm : None,
+    pub fn new(speak : Sender<SpeakMessage>, config : &Config, offline : Arc<AtomicBool>) -> CommandDirector {
+        let storage = Storage::new(&config.data_dir);
+        let http = HttpService::new(&config.http, offline.clone(), storage.clone());
+        let timers = TimerService::new(speak.clone());
+        let mut commands : Vec<Box<dyn Command>> = Vec::new();
+        // custom commands go first, since their trigger phrases were picked on purpose
+        for custom in CustomCommand::load_dir(CUSTOM_COMMAND_DIR, &http) {
+            commands.push(Box::new(custom));
+        }
+        for plugin in PluginCommand::load_dir(PLUGIN_DIR) {
+            commands.push(Box::new(plugin));
+        }
+        if let Some(shell) = ShellCommand::load(SHELL_COMMAND_FILE) {
+            commands.push(Box::new(shell));
+        }
+        // add commands here
+        commands.push(Box::new(TestCommand{})); // I should probably make a ::new() for this
+        commands.push(Box::new(WeatherCommand::new(&config.weather, http.clone(), storage.clone())));
+        commands.push(Box::new(JokeCommand::new(http.clone(), storage.clone())));
+        // before dice, so "roll initiative" isn't taken for a roll
+        commands.push(Box::new(InitiativeCommand::new(storage.clone())));
+        commands.push(Box::new(DiceCommand::new(storage.clone())));
+        commands.push(Box::new(RandomCommand{}));
+        // before the other timers, so "remind me to start a pomodoro" is a reminder
+        commands.push(Box::new(ReminderCommand::new(timers.clone(), storage.clone())));
+        // before lists and timers, so a note can be about them
+        commands.push(Box::new(NoteCommand::new(storage.clone())));
+        // before alarms, so "pause the pomodoro timer" doesn't set a timer
+        commands.push(Box::new(RoutineCommand::new(timers.clone())));
+        commands.push(Box::new(AlarmCommand::new(timers.clone())));
+        // before the clock, so "how long has it been" goes to a stopwatch
+        commands.push(Box::new(StopwatchCommand::new(timers.clone())));
+        commands.push(Box::new(ClockCommand{}));
+        commands.push(Box::new(ListCommand::new(storage.clone())));
+        // last, since it takes anything that reads as math
+        commands.push(Box::new(CalculatorCommand{}));
+        commands.insert(0, Box::new(HelpCommand::new(&commands)));
+        return CommandDirector {
+            commands : commands.into_iter().map(|c| Arc::new(Mutex::new(c))).collect(),
+            session : None,
+            pending : None,
+            workers : WorkerPool::new(WORKER_THREADS),
             speak,
+            offline,
         };
-        // add commands here
-        cd.commands.push(Box::new(TestCommand{})); // I should probably make a ::new() for this
-        cd.commands.push(Box::new(WeatherCommand::new(String::from("Drums"))));
-        cd.commands.push(Box::new(JokeCommand{}));
-        cd.commands.push(Box::new(DiceCommand{}));
-        cd.commands.push(Box::new(AlarmCommand{}));
-        cd.commands.insert(0, Box::new(HelpCommand::new(&cd.commands)));
-        return cd;
     }
 
     // takes in text, then determines which command it matches and executes it
     pub fn dispatch_command(&mut self, text : String) -> DispatchResult {
-        // if there's a leftover function from last time
-        if self.next_comm.is_some() {
-            let result = self.commands[self.next_comm.unwrap()].effect(text, self.speak.clone());
-            match result {
-                CommandResult::Done => {
-                    self.next_comm = None;
-                    return DispatchResult::Done
-                },
-                CommandResult::Continue => {
-                    // next_comm stays the same
-                    return DispatchResult::Continue;
-                }
+        let raw = self.session.as_ref().is_some_and(|s| lock(&self.commands[s.command]).takes_raw_text());
+        if raw {
+            if self.end_if_offline() {
+                return DispatchResult::Done;
+            }
+            let session = self.session.take().unwrap();
+            let state = session.state;
+            return self.run(session.command, move |command, speak| command.resume(state, text, speak));
+        }
+        // drop the conversation if it's been waiting too long, and treat this as a new command
+        if self.session.as_ref().is_some_and(|s| s.last_active.elapsed() > SESSION_TIMEOUT) {
+            self.end_session();
+        }
+        // check for the phrases that work no matter what command is running
+        let trimmed = text.trim();
+        if CANCEL_PHRASES.contains(&trimmed) {
+            if self.session.is_some() {
+                self.end_session();
+                self.speak.send(SpeakMessage::Say(String::from("Okay, never mind."))).unwrap();
+            } else {
+                self.speak.send(SpeakMessage::Say(String::from("There's nothing to cancel."))).unwrap();
             }
+            return DispatchResult::Done;
         }
-        // if there's not a leftover function from last time
-        for (index, command) in &mut self.commands.iter_mut().enumerate() {
+        if REPEAT_PHRASES.contains(&trimmed) {
+            self.speak.send(SpeakMessage::Repeat).unwrap();
+            // if a command was waiting on an answer, keep waiting for it
+            if let Some(session) = &mut self.session {
+                session.last_active = Instant::now();
+                return DispatchResult::Continue;
+            }
+            return DispatchResult::Done;
+        }
+        if OFFLINE_PHRASES.contains(&trimmed) || ONLINE_PHRASES.contains(&trimmed) {
+            let go_offline = OFFLINE_PHRASES.contains(&trimmed);
+            self.offline.store(go_offline, Ordering::SeqCst);
+            let answer = if go_offline {
+                "Offline mode is on. I won't use the internet until you say \"go online\"."
+            } else {
+                "Offline mode is off."
+            };
+            self.speak.send(SpeakMessage::Say(String::from(answer))).unwrap();
+            if let Some(session) = &mut self.session {
+                session.last_active = Instant::now();
+                return DispatchResult::Continue;
+            }
+            return DispatchResult::Done;
+        }
+        // if there's a conversation left over from last time
+        if self.end_if_offline() {
+            return DispatchResult::Done;
+        }
+        if let Some(session) = self.session.take() {
+            let state = session.state;
+            return self.run(session.command, move |command, speak| command.resume(state, text, speak));
+        }
+        // if there's not a conversation left over from last time
+        let mut busy = false;
+        for index in 0..self.commands.len() {
+            // a command running on a worker stays locked until it's done, and waiting on it would hold up listening
+            let command = match self.commands[index].try_lock() {
+                Ok(c) => {c},
+                Err(TryLockError::Poisoned(e)) => {e.into_inner()},
+                Err(TryLockError::WouldBlock) => {
+                    busy = true;
+                    continue;
+                },
+            };
             if command.recognize(text.clone()) {
-                let result = command.effect(text, self.speak.clone());
-                match result {
-                    CommandResult::Done => {return DispatchResult::Done},
-                    CommandResult::Continue => {
-                        self.next_comm = Some(index);
-                        return DispatchResult::Continue;
-                    }
+                if command.uses_internet() && self.offline.load(Ordering::SeqCst) {
+                    self.say_offline(&command.name());
+                    return DispatchResult::Done;
                 }
+                drop(command);
+                return self.run(index, move |command, speak| command.effect(text, speak));
             }
         }
+        if busy {
+            // it might have been for the command that's still going
+            self.speak.send(SpeakMessage::Say(String::from("I'm still working on that. Please try again in a moment."))).unwrap();
+            return DispatchResult::Done;
+        }
         self.speak.send(SpeakMessage::Say(String::from("I'm not sure what you're asking for. Please try again."))).unwrap();
         return DispatchResult::Done;
     }
+
+    // checks on a command running in the background, giving back its result once it's finished
+    // also ends a conversation like dictation once it's been quiet long enough, so the mic isn't left listening
+    pub fn poll(&mut self) -> Option<DispatchResult> {
+        if self.session.as_ref().is_some_and(|s| s.last_active.elapsed() > RAW_SESSION_TIMEOUT
+            && lock(&self.commands[s.command]).takes_raw_text()) {
+            self.end_session();
+            return Some(DispatchResult::Done);
+        }
+        let pending = self.pending.as_mut()?;
+        match pending.result.try_recv() {
+            Ok(result) => {
+                let command = pending.command;
+                self.pending = None;
+                return Some(self.handle_result(command, result));
+            },
+            Err(TryRecvError::Empty) => {
+                if !pending.cued && pending.started.elapsed() > STILL_WORKING_AFTER {
+                    // an Ack so that "repeat that" still repeats the real answer once it arrives
+                    self.speak.send(SpeakMessage::Ack(String::from("Still working on it."))).unwrap();
+                    pending.cued = true;
+                }
+                return None;
+            },
+            Err(TryRecvError::Disconnected) => {
+                // the worker panicked partway through the command
+                let name = lock(&self.commands[pending.command]).name();
+                eprintln!("Error: the {} command stopped without finishing", name);
+                self.pending = None;
+                self.speak.send(SpeakMessage::Say(String::from("Something went wrong with that command."))).unwrap();
+                return Some(DispatchResult::Done);
+            }
+        }
+    }
+
+    // runs part of a command, on a worker thread if it could take a while
+    fn run(&mut self, index : usize, job : impl FnOnce(&mut dyn Command, Sender<SpeakMessage>) -> CommandResult + Send + 'static) -> DispatchResult {
+        let shared = self.commands[index].clone();
+        let speak = self.speak.clone();
+        if !lock(&shared).runs_long() {
+            let result = job(lock(&shared).as_mut(), speak);
+            return self.handle_result(index, result);
+        }
+        let (resulttx, resultrx) = mpsc::channel::<CommandResult>();
+        self.workers.execute(move || {
+            let _ = resulttx.send(job(lock(&shared).as_mut(), speak));
+        });
+        self.pending = Some(Pending {
+            command : index,
+            result : resultrx,
+            started : Instant::now(),
+            cued : false,
+        });
+        return DispatchResult::Working;
+    }
+
+    // keeps track of the conversation if the command needs more input
+    fn handle_result(&mut self, command : usize, result : CommandResult) -> DispatchResult {
+        match result {
+            CommandResult::Done => {
+                return DispatchResult::Done;
+            },
+            CommandResult::Continue(state) => {
+                self.session = Some(Session {
+                    command,
+                    state,
+                    last_active : Instant::now(),
+                });
+                return DispatchResult::Continue;
+            }
+        }
+    }
+
+    // ends the conversation if its command needs the internet and offline mode has been turned on since it started,
+    // by voice or from the tray, giving back whether it did
+    fn end_if_offline(&mut self) -> bool {
+        let session = match &self.session {
+            Some(s) => {s},
+            None => {return false},
+        };
+        let command = lock(&self.commands[session.command]);
+        if !command.uses_internet() || !self.offline.load(Ordering::SeqCst) {
+            return false;
+        }
+        let name = command.name();
+        drop(command);
+        self.end_session();
+        self.say_offline(&name);
+        return true;
+    }
+
+    // tells the user a command can't be used because of offline mode
+    fn say_offline(&self, name : &str) {
+        self.speak.send(SpeakMessage::Say(format!(
+            "The {} command needs the internet, but I'm in offline mode. Say \"go online\" to turn it off.", name))).unwrap();
+    }
+
+    // ends the current conversation, if there is one, for when it's stopped from outside (like the tray)
+    // a command still running in the background is forgotten too, so its answer can't start listening again
+    pub fn end_conversation(&mut self) {
+        self.pending = None;
+        self.end_session();
+    }
+
+    // lets the command in the current conversation clean up, then forgets the conversation
+    fn end_session(&mut self) {
+        if let Some(session) = self.session.take() {
+            lock(&self.commands[session.command]).end(session.state, self.speak.clone());
+        }
+    }
 }
@@ -2,10 +2,43 @@ use crate::commands::{Command, CommandResult};
 use std::sync::mpsc::{Sender};
 use crate::SpeakMessage;
 use text2num::{Language, replace_numbers_in_text};
-use std::thread;
+use crate::timers::{Timer, TimerService};
 use std::time::Duration;
 
-pub struct AlarmCommand {}
+// how many milliseconds are in a unit of time, like "minutes"
+pub fn unit_millis(word : &str) -> Option<u64> {
+    if word.contains("second") {
+        return Some(1000);
+    } else if word.contains("minute") {
+        return Some(60000);
+    } else if word.contains("hour") {
+        return Some(3600000);
+    }
+    return None;
+}
+
+// adds up every amount of time in some text, like "an hour and five minutes"
+pub fn parse_duration(text : &str) -> Duration {
+    let en = Language::english();
+    let numtext = replace_numbers_in_text(text, &en, 0.0);
+    let words : Vec<&str> = numtext.split_whitespace().collect();
+    let mut millis = 0;
+    for pair in words.windows(2) {
+        if let (Ok(n), Some(unit)) = (pair[0].parse::<u64>(), unit_millis(pair[1])) {
+            millis += n * unit;
+        }
+    }
+    return Duration::from_millis(millis);
+}
+
+pub struct AlarmCommand {
+    timers : TimerService,
+}
+impl AlarmCommand {
+    pub fn new(timers : TimerService) -> AlarmCommand {
+        return AlarmCommand{timers};
+    }
+}
 impl Command for AlarmCommand {
     fn name(&self) -> String {
         return String::from("Alarm");
@@ -19,42 +52,22 @@ impl Command for AlarmCommand {
     fn uses_internet(&self) -> bool {
         return false;
     }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("timer"), String::from("timers")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("set a timer for five minutes")];
+    }
     fn recognize(&self, text : String) -> bool {
         return text.contains("timer") || text.contains("alarm");
     }
     fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
-        let en = Language::english();
-        let numtext = replace_numbers_in_text(&text, &en, 0.0);
-        let words : Vec<&str> = numtext.split_whitespace().collect();
-
         if text.contains("timer") {
             // timer stuff
-            let mut millis = 0;
-            for pair in words.windows(2) {
-                if pair[1].contains("second") {
-                    match pair[0].parse::<u64>() {
-                        Ok(n) => millis += n * 1000,
-                        Err(_) => {},
-                    }
-                } else if pair[1].contains("minute") {
-                    match pair[0].parse::<u64>() {
-                        Ok(n) => millis += n * 60000,
-                        Err(_) => {},
-                    }
-                } else if pair[1].contains("hour") {
-                    match pair[0].parse::<u64>() {
-                        Ok(n) => millis += n * 3600000,
-                        Err(_) => {},
-                    }
-                } 
-            }
+            let length = parse_duration(&text);
             // temporary message to make sure it's adding up times correctly
             speak.send(SpeakMessage::Say(String::from("Timer set."))).unwrap();
-            // set a thread to wait for the allotted amount of time
-            thread::spawn(move || {
-                thread::sleep(Duration::from_millis(millis));
-                speak.send(SpeakMessage::Say(String::from("Your timer has run out."))).unwrap();
-            });
+            self.timers.start(Timer::countdown("", length, "Your timer has run out."));
         } else if text.contains("alarm") {
             // alarm stuff
         }
@@ -0,0 +1,414 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use text2num::{Language, replace_numbers_in_text};
+
+// words around a question that don't change the math
+const FILLER_WORDS : [&str; 16] = ["what", "is", "what's", "whats", "equals", "equal", "calculate", "compute", "please",
+    "tell", "me", "does", "by", "the", "much", "are"];
+
+// what a unit measures, since only units of the same kind can be converted
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Length,
+    Mass,
+    Volume,
+    Temperature,
+}
+
+struct Unit {
+    names : &'static [&'static str], // the singular, then the plural, then anything else it might be called
+    kind : Kind,
+    // how to get to the base unit (meters, grams, liters, or kelvin), which is the value times this plus offset
+    factor : f64,
+    offset : f64,
+}
+
+const UNITS : [Unit; 29] = [
+    Unit{names : &["millimeter", "millimeters", "millimetre", "millimetres"], kind : Kind::Length, factor : 0.001, offset : 0.0},
+    Unit{names : &["centimeter", "centimeters", "centimetre", "centimetres"], kind : Kind::Length, factor : 0.01, offset : 0.0},
+    Unit{names : &["meter", "meters", "metre", "metres"], kind : Kind::Length, factor : 1.0, offset : 0.0},
+    Unit{names : &["kilometer", "kilometers", "kilometre", "kilometres"], kind : Kind::Length, factor : 1000.0, offset : 0.0},
+    Unit{names : &["inch", "inches"], kind : Kind::Length, factor : 0.0254, offset : 0.0},
+    Unit{names : &["foot", "feet"], kind : Kind::Length, factor : 0.3048, offset : 0.0},
+    Unit{names : &["yard", "yards"], kind : Kind::Length, factor : 0.9144, offset : 0.0},
+    Unit{names : &["mile", "miles"], kind : Kind::Length, factor : 1609.344, offset : 0.0},
+    Unit{names : &["milligram", "milligrams"], kind : Kind::Mass, factor : 0.001, offset : 0.0},
+    Unit{names : &["gram", "grams"], kind : Kind::Mass, factor : 1.0, offset : 0.0},
+    Unit{names : &["kilogram", "kilograms", "kilo", "kilos"], kind : Kind::Mass, factor : 1000.0, offset : 0.0},
+    Unit{names : &["ounce", "ounces"], kind : Kind::Mass, factor : 28.349523125, offset : 0.0},
+    Unit{names : &["pound", "pounds"], kind : Kind::Mass, factor : 453.59237, offset : 0.0},
+    Unit{names : &["stone", "stone", "stones"], kind : Kind::Mass, factor : 6350.29318, offset : 0.0},
+    Unit{names : &["ton", "tons"], kind : Kind::Mass, factor : 907184.74, offset : 0.0},
+    Unit{names : &["metric ton", "metric tons", "tonne", "tonnes"], kind : Kind::Mass, factor : 1000000.0, offset : 0.0},
+    Unit{names : &["milliliter", "milliliters", "millilitre", "millilitres"], kind : Kind::Volume, factor : 0.001, offset : 0.0},
+    Unit{names : &["liter", "liters", "litre", "litres"], kind : Kind::Volume, factor : 1.0, offset : 0.0},
+    Unit{names : &["teaspoon", "teaspoons"], kind : Kind::Volume, factor : 0.00492892159375, offset : 0.0},
+    Unit{names : &["tablespoon", "tablespoons"], kind : Kind::Volume, factor : 0.01478676478125, offset : 0.0},
+    Unit{names : &["fluid ounce", "fluid ounces"], kind : Kind::Volume, factor : 0.0295735295625, offset : 0.0},
+    Unit{names : &["cup", "cups"], kind : Kind::Volume, factor : 0.2365882365, offset : 0.0},
+    Unit{names : &["pint", "pints"], kind : Kind::Volume, factor : 0.473176473, offset : 0.0},
+    Unit{names : &["quart", "quarts"], kind : Kind::Volume, factor : 0.946352946, offset : 0.0},
+    Unit{names : &["gallon", "gallons"], kind : Kind::Volume, factor : 3.785411784, offset : 0.0},
+    Unit{names : &["cubic meter", "cubic meters", "cubic metre", "cubic metres"], kind : Kind::Volume, factor : 1000.0, offset : 0.0},
+    Unit{names : &["degree celsius", "degrees celsius", "celsius", "centigrade", "degrees centigrade"], kind : Kind::Temperature, factor : 1.0, offset : 273.15},
+    Unit{names : &["degree fahrenheit", "degrees fahrenheit", "fahrenheit"], kind : Kind::Temperature, factor : 5.0 / 9.0, offset : 273.15 - 32.0 * 5.0 / 9.0},
+    Unit{names : &["kelvin", "kelvin", "kelvins", "degrees kelvin"], kind : Kind::Temperature, factor : 1.0, offset : 0.0},
+];
+
+// why some math couldn't be done
+enum CalcError {
+    Syntax, // it wasn't math at all, so some other command might want it
+    Math(String), // it was math, but there's no answer, with something to say about why
+}
+
+// reads spoken math one word at a time, working it out as it goes
+struct Calc {
+    words : Vec<String>,
+    pos : usize,
+    operations : usize, // how many operations were done, since a bare number isn't really a question
+}
+impl Calc {
+    fn new(words : Vec<String>) -> Calc {
+        return Calc{words, pos : 0, operations : 0};
+    }
+
+    // works out the whole thing, failing if any words are left over
+    fn evaluate(&mut self) -> Result<f64, CalcError> {
+        let value = self.sum()?;
+        if self.pos < self.words.len() {
+            return Err(CalcError::Syntax);
+        }
+        return Ok(value);
+    }
+
+    fn peek(&self) -> Option<&str> {
+        return self.words.get(self.pos).map(|w| w.as_str());
+    }
+
+    // skips over a word if it's next, telling whether it was
+    fn take(&mut self, word : &str) -> bool {
+        if self.peek() == Some(word) {
+            self.pos += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn sum(&mut self) -> Result<f64, CalcError> {
+        let mut value = self.product()?;
+        loop {
+            if self.take("plus") {
+                value += self.product()?;
+            } else if self.take("minus") {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+            self.operations += 1;
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, CalcError> {
+        let mut value = self.power()?;
+        loop {
+            if self.take("times") || self.take("multiplied") || self.take("x") || self.take("of") {
+                value *= self.power()?;
+            } else if self.take("divided") || self.take("over") {
+                let divisor = self.power()?;
+                if divisor == 0.0 {
+                    return Err(CalcError::Math(String::from("You can't divide by zero.")));
+                }
+                value /= divisor;
+            } else if self.take("mod") || self.take("modulo") {
+                let divisor = self.power()?;
+                if divisor == 0.0 {
+                    return Err(CalcError::Math(String::from("You can't divide by zero.")));
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+            self.operations += 1;
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, CalcError> {
+        let base = self.unary()?;
+        // "to the power of", "raised to", and "raised to the power of", with "the" already left out
+        let raised = self.take("raised");
+        if raised || (self.peek() == Some("to") && self.words.get(self.pos + 1).is_some_and(|w| w == "power")) {
+            self.take("to");
+            if self.take("power") {
+                self.take("of");
+            }
+            self.operations += 1;
+            return Ok(base.powf(self.power()?));
+        }
+        return Ok(base);
+    }
+
+    fn unary(&mut self) -> Result<f64, CalcError> {
+        if self.take("negative") || self.take("minus") {
+            return Ok(-self.unary()?);
+        }
+        if self.take("square") {
+            if !self.take("root") {
+                return Err(CalcError::Syntax);
+            }
+            self.take("of");
+            self.operations += 1;
+            let value = self.unary()?;
+            if value < 0.0 {
+                return Err(CalcError::Math(String::from("I can't take the square root of a negative number.")));
+            }
+            return Ok(value.sqrt());
+        }
+        if self.take("cube") {
+            if !self.take("root") {
+                return Err(CalcError::Syntax);
+            }
+            self.take("of");
+            self.operations += 1;
+            return Ok(self.unary()?.cbrt());
+        }
+        let mut value = self.primary()?;
+        loop {
+            if self.take("squared") {
+                value = value * value;
+            } else if self.take("cubed") {
+                value = value * value * value;
+            } else if self.take("percent") {
+                value /= 100.0;
+            } else {
+                return Ok(value);
+            }
+            self.operations += 1;
+        }
+    }
+
+    // a number, possibly with a fraction, like "2", "a half", "3 quarters", or "2 and a half"
+    fn primary(&mut self) -> Result<f64, CalcError> {
+        let word = self.peek().ok_or(CalcError::Syntax)?.to_string();
+        self.pos += 1;
+        if word == "pi" {
+            return Ok(std::f64::consts::PI);
+        }
+        // "a half" is one half, and "a 100" is just 100, but "a" on its own isn't a number
+        let article = word == "a" || word == "an";
+        let mut value = if article {1.0} else {word.replace(',', "").parse::<f64>().or(Err(CalcError::Syntax))?};
+        let mut found = !article;
+        if let Some(n) = self.peek().and_then(|w| w.parse::<f64>().ok()).filter(|_| article) {
+            self.pos += 1;
+            value = n;
+            found = true;
+        }
+        if let Some(denominator) = self.peek().and_then(denominator_of) {
+            self.pos += 1;
+            value /= denominator;
+            found = true;
+            self.operations += 1;
+        }
+        if !found {
+            return Err(CalcError::Syntax);
+        }
+        // "2 and a half"
+        if self.peek() == Some("and") && self.words.get(self.pos + 1).is_some_and(|w| w == "a" || w == "1") {
+            if let Some(denominator) = self.words.get(self.pos + 2).and_then(|w| denominator_of(w)) {
+                self.pos += 3;
+                value += 1.0 / denominator;
+            }
+        }
+        return Ok(value);
+    }
+}
+
+// the number under a fraction word, like 4 for "quarter" or 5 for "5th" (which is how text2num writes "fifth")
+fn denominator_of(word : &str) -> Option<f64> {
+    match word {
+        "half" | "halves" => {return Some(2.0)},
+        "third" | "thirds" => {return Some(3.0)},
+        "quarter" | "quarters" | "fourth" | "fourths" => {return Some(4.0)},
+        _ => {},
+    }
+    let digits = word.trim_end_matches('s');
+    for suffix in ["st", "nd", "rd", "th"] {
+        if let Some(n) = digits.strip_suffix(suffix).and_then(|d| d.parse::<f64>().ok()) {
+            if n > 1.0 {
+                return Some(n);
+            }
+        }
+    }
+    return None;
+}
+
+// turns spoken text into words the calculator can read, with numbers as digits and symbols as words
+fn words_of(text : &str) -> Vec<String> {
+    let spaced = text.replace('+', " plus ").replace('*', " times ").replace('/', " over ")
+        .replace('^', " to power of ").replace('%', " percent ").replace('?', " ");
+    let converted = replace_numbers_in_text(&spaced, &Language::english(), 0.0);
+    return converted.split_whitespace()
+        .map(|w| w.to_lowercase())
+        .filter(|w| !FILLER_WORDS.contains(&w.as_str()))
+        .collect();
+}
+
+// finds the units mentioned in the words, giving back where each starts, how many words it takes, and which unit it is
+fn units_in(words : &[String]) -> Vec<(usize, usize, &'static Unit)> {
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        // the longest name wins, so "fluid ounces" isn't taken for "ounces"
+        let mut best : Option<(usize, &Unit)> = None;
+        for unit in UNITS.iter() {
+            for name in unit.names {
+                let length = name.split(' ').count();
+                if i + length <= words.len() && words[i..i + length].join(" ") == *name && best.is_none_or(|(l, _)| l < length) {
+                    best = Some((length, unit));
+                }
+            }
+        }
+        match best {
+            Some((length, unit)) => {
+                found.push((i, length, unit));
+                i += length;
+            },
+            None => {i += 1},
+        }
+    }
+    return found;
+}
+
+// reads a conversion like "5 miles to kilometers" or "how many cups in a gallon"
+// gives back the amount and the units to convert from and to, or None if it isn't one
+fn parse_conversion(text : &str) -> Option<Result<(f64, &'static Unit, &'static Unit), CalcError>> {
+    let asking = text.contains("how many") || text.contains("how much");
+    let words : Vec<String> = words_of(text).into_iter().filter(|w| !["convert", "how", "many", "there"].contains(&w.as_str())).collect();
+    let units = units_in(&words);
+    if units.len() != 2 {
+        return None;
+    }
+    let (from, to, amount) = if asking {
+        // "how many cups in a gallon", where the amount comes between the units
+        let (start, length, to) = units[0];
+        let (from_start, _, from) = units[1];
+        if start != 0 {
+            return None;
+        }
+        (from, to, &words[start + length..from_start])
+    } else {
+        // "5 miles to kilometers", where the amount comes first
+        let (start, _, from) = units[0];
+        let (_, _, to) = units[1];
+        (from, to, &words[..start])
+    };
+    let connectors = ["to", "in", "into", "as"];
+    let mut amount : Vec<String> = amount.iter().filter(|w| !connectors.contains(&w.as_str())).cloned().collect();
+    // "a quarter of a cup" is a quarter of one cup
+    while amount.last().is_some_and(|w| w == "a" || w == "an" || w == "of") {
+        amount.pop();
+    }
+    // something like "ounces", when converting to a volume, means fluid ounces
+    let fluid = |u : &'static Unit, other : &'static Unit| -> &'static Unit {
+        if u.names[0] == "ounce" && other.kind == Kind::Volume {
+            return UNITS.iter().find(|f| f.names[0] == "fluid ounce").unwrap_or(u);
+        }
+        return u;
+    };
+    let (from, to) = (fluid(from, to), fluid(to, from));
+    if from.kind != to.kind {
+        return Some(Err(CalcError::Math(format!("I can't convert {} to {}.", from.names[1], to.names[1]))));
+    }
+    if amount.is_empty() {
+        return Some(Ok((1.0, from, to)));
+    }
+    return Some(Calc::new(amount).evaluate().map(|a| (a, from, to)));
+}
+
+// a number rounded to something reasonable to say, and whether it had to be rounded
+fn spoken_number(value : f64) -> (String, bool) {
+    let decimals = if value.abs() >= 100.0 || value == 0.0 {
+        0
+    } else {
+        // three significant figures, so small numbers don't all round to 0
+        (2 - value.abs().log10().floor() as i32).clamp(0, 6) as usize
+    };
+    let rounded = format!("{:.*}", decimals, value);
+    let rounded = if rounded.contains('.') {rounded.trim_end_matches('0').trim_end_matches('.').to_string()} else {rounded};
+    let exact = (rounded.parse::<f64>().unwrap_or(value) - value).abs() <= 1e-9 * value.abs().max(1.0);
+    let spoken = match rounded.strip_prefix('-') {
+        Some(positive) if positive != "0" => {format!("minus {}", positive)},
+        Some(_) => {String::from("0")},
+        None => {rounded},
+    };
+    return (spoken, !exact);
+}
+
+// works out spoken arithmetic and converts between units
+pub struct CalculatorCommand {}
+impl CalculatorCommand {
+    // the answer to some math, or None if it isn't math
+    fn calculate(text : &str) -> Option<Result<f64, CalcError>> {
+        let mut calc = Calc::new(words_of(text));
+        let result = calc.evaluate();
+        if let Err(CalcError::Syntax) = result {
+            return None;
+        }
+        if result.is_ok() && calc.operations == 0 {
+            return None;
+        }
+        return Some(result);
+    }
+}
+impl Command for CalculatorCommand {
+    fn name(&self) -> String {
+        return String::from("Calculator");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command does math and converts between units.");
+    }
+    fn help(&self) -> String {
+        return String::from("Ask a math question, like \"what is forty two times seventeen\", \"square root of two\", or \"fifteen percent of eighty\". You can add, subtract, multiply, divide, and use powers, square and cube roots, percentages, and fractions like \"two and a half\". To convert units, say something like \"convert five miles to kilometers\" or \"how many cups in a gallon\". Lengths, weights, volumes including cooking measurements, and temperatures can be converted.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("math"), String::from("calculate"), String::from("convert"), String::from("unit conversion")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("what is forty two times seventeen"), String::from("fifteen percent of eighty"),
+            String::from("convert five miles to kilometers"), String::from("how many tablespoons in a cup")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        return parse_conversion(&text).is_some() || CalculatorCommand::calculate(&text).is_some();
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let answer = if let Some(conversion) = parse_conversion(&text) {
+            match conversion {
+                Ok((amount, from, to)) => {
+                    let converted = ((amount * from.factor + from.offset) - to.offset) / to.factor;
+                    let (amount_said, _) = spoken_number(amount);
+                    let (converted_said, rounded) = spoken_number(converted);
+                    let from_name = if amount == 1.0 {from.names[0]} else {from.names[1]};
+                    let to_name = if converted_said == "1" {to.names[0]} else {to.names[1]};
+                    format!("{} {} is {}{} {}.", amount_said, from_name, if rounded {"about "} else {""}, converted_said, to_name)
+                },
+                Err(CalcError::Math(e)) => {e},
+                Err(CalcError::Syntax) => {String::from("I couldn't make out how much to convert.")},
+            }
+        } else {
+            match CalculatorCommand::calculate(&text) {
+                Some(Ok(value)) if value.is_finite() => {
+                    let (said, rounded) = spoken_number(value);
+                    format!("That's {}{}.", if rounded {"about "} else {""}, said)
+                },
+                Some(Ok(_)) => {String::from("That number is too big for me.")},
+                Some(Err(CalcError::Math(e))) => {e},
+                _ => {String::from("I couldn't work that out.")},
+            }
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+}
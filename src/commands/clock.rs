@@ -0,0 +1,374 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use text2num::{Language, replace_numbers_in_text};
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use crate::commands::spoken::{WEEKDAYS, capitalize};
+
+const MONTHS : [&str; 12] = ["january", "february", "march", "april", "may", "june", "july", "august", "september",
+    "october", "november", "december"];
+
+// places people ask about that aren't the name of a time zone's city
+const ZONE_ALIASES : [(&str, &str); 36] = [
+    ("pacific time", "America/Los_Angeles"), ("mountain time", "America/Denver"), ("central time", "America/Chicago"),
+    ("eastern time", "America/New_York"), ("utc", "UTC"), ("gmt", "GMT"), ("greenwich", "GMT"),
+    ("california", "America/Los_Angeles"), ("san francisco", "America/Los_Angeles"), ("seattle", "America/Los_Angeles"),
+    ("texas", "America/Chicago"), ("boston", "America/New_York"), ("washington", "America/New_York"),
+    ("florida", "America/New_York"), ("hawaii", "Pacific/Honolulu"), ("alaska", "America/Anchorage"),
+    ("canada", "America/Toronto"), ("mexico", "America/Mexico_City"), ("brazil", "America/Sao_Paulo"),
+    ("england", "Europe/London"), ("britain", "Europe/London"), ("the uk", "Europe/London"), ("ireland", "Europe/Dublin"),
+    ("france", "Europe/Paris"), ("germany", "Europe/Berlin"), ("spain", "Europe/Madrid"), ("italy", "Europe/Rome"),
+    ("russia", "Europe/Moscow"), ("india", "Asia/Kolkata"), ("delhi", "Asia/Kolkata"), ("mumbai", "Asia/Kolkata"),
+    ("china", "Asia/Shanghai"), ("beijing", "Asia/Shanghai"), ("japan", "Asia/Tokyo"), ("korea", "Asia/Seoul"),
+    ("australia", "Australia/Sydney"),
+];
+
+// how a date falls each year
+#[derive(Clone, Copy)]
+enum Rule {
+    Fixed(u32, u32), // month and day
+    Nth(u32, Weekday, i32), // the nth weekday of a month, with -1 as the last one
+    Easter,
+}
+impl Rule {
+    fn on(&self, year : i32) -> Option<NaiveDate> {
+        match *self {
+            Rule::Fixed(month, day) => {return NaiveDate::from_ymd_opt(year, month, day)},
+            Rule::Nth(month, weekday, n) if n > 0 => {return NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8)},
+            Rule::Nth(month, weekday, _) => {
+                // the last one is the fifth if there is one, otherwise the fourth
+                return NaiveDate::from_weekday_of_month_opt(year, month, weekday, 5)
+                    .or_else(|| NaiveDate::from_weekday_of_month_opt(year, month, weekday, 4));
+            },
+            Rule::Easter => {
+                // the anonymous Gregorian algorithm
+                let a = year % 19;
+                let b = year / 100;
+                let c = year % 100;
+                let d = (19 * a + b - b / 4 - (b - (8 * b + 13) / 25) + 15) % 30;
+                let e = (32 + 2 * (b % 4) + 2 * (c / 4) - d - c % 4) % 7;
+                let f = d + e - 7 * ((a + 11 * d + 22 * e) / 451) + 114;
+                return NaiveDate::from_ymd_opt(year, (f / 31) as u32, (f % 31 + 1) as u32);
+            },
+        }
+    }
+}
+
+struct Holiday {
+    name : &'static str,
+    phrases : &'static [&'static str], // how it might come out of speech recognition
+    rule : Rule,
+}
+
+const HOLIDAYS : [Holiday; 19] = [
+    Holiday{name : "New Year's Day", phrases : &["new year's day", "new years day", "new year's", "new years"], rule : Rule::Fixed(1, 1)},
+    Holiday{name : "Martin Luther King Day", phrases : &["martin luther king", "m l k day"], rule : Rule::Nth(1, Weekday::Mon, 3)},
+    Holiday{name : "Valentine's Day", phrases : &["valentine's", "valentines"], rule : Rule::Fixed(2, 14)},
+    Holiday{name : "Presidents' Day", phrases : &["presidents day", "president's day", "presidents' day"], rule : Rule::Nth(2, Weekday::Mon, 3)},
+    Holiday{name : "Saint Patrick's Day", phrases : &["patrick's day", "patricks day"], rule : Rule::Fixed(3, 17)},
+    Holiday{name : "April Fools' Day", phrases : &["april fool"], rule : Rule::Fixed(4, 1)},
+    Holiday{name : "Easter", phrases : &["easter"], rule : Rule::Easter},
+    Holiday{name : "Mother's Day", phrases : &["mother's day", "mothers day"], rule : Rule::Nth(5, Weekday::Sun, 2)},
+    Holiday{name : "Memorial Day", phrases : &["memorial day"], rule : Rule::Nth(5, Weekday::Mon, -1)},
+    Holiday{name : "Father's Day", phrases : &["father's day", "fathers day"], rule : Rule::Nth(6, Weekday::Sun, 3)},
+    Holiday{name : "Independence Day", phrases : &["independence day", "fourth of july"], rule : Rule::Fixed(7, 4)},
+    Holiday{name : "Labor Day", phrases : &["labor day", "labour day"], rule : Rule::Nth(9, Weekday::Mon, 1)},
+    Holiday{name : "Halloween", phrases : &["halloween"], rule : Rule::Fixed(10, 31)},
+    Holiday{name : "Veterans Day", phrases : &["veterans day", "veteran's day"], rule : Rule::Fixed(11, 11)},
+    Holiday{name : "Thanksgiving", phrases : &["thanksgiving"], rule : Rule::Nth(11, Weekday::Thu, 4)},
+    Holiday{name : "Christmas Eve", phrases : &["christmas eve"], rule : Rule::Fixed(12, 24)},
+    Holiday{name : "Christmas", phrases : &["christmas"], rule : Rule::Fixed(12, 25)},
+    Holiday{name : "New Year's Eve", phrases : &["new year's eve", "new years eve"], rule : Rule::Fixed(12, 31)},
+    Holiday{name : "Boxing Day", phrases : &["boxing day"], rule : Rule::Fixed(12, 26)},
+];
+
+// a day that was asked about
+enum When {
+    Date(NaiveDate), // a particular day, like "tomorrow" or "March 3rd 2027"
+    Yearly(Rule), // something that happens every year, where the year still has to be worked out
+}
+
+// which year a yearly date is taken from
+#[derive(PartialEq)]
+enum Direction {
+    ThisYear,
+    Next, // the next time it comes around, for counting down
+    Last, // the last time it came around, for counting up
+}
+
+// tells the time and date, here or anywhere else, and counts days
+pub struct ClockCommand {}
+impl ClockCommand {
+    fn asks_time(text : &str) -> bool {
+        return text.contains("what time") || text.contains("the time") || text.contains("time is it") || text.contains("time in ");
+    }
+
+    fn asks_until(text : &str) -> bool {
+        return ["until", "till", "til ", "how long to", "days to"].iter().any(|w| text.contains(w));
+    }
+
+    fn asks_since(text : &str) -> bool {
+        return text.contains("since") || text.contains("ago");
+    }
+
+    // the time zone for a place, like "tokyo", "new york", or "pacific time"
+    fn find_zone(place : &str) -> Option<Tz> {
+        let place = place.trim();
+        if let Some((_, name)) = ZONE_ALIASES.iter().find(|(alias, _)| *alias == place) {
+            return name.parse().ok();
+        }
+        for zone in TZ_VARIANTS {
+            let name = zone.name().to_lowercase();
+            let city = name.rsplit('/').next().unwrap_or(&name).replace('_', " ");
+            if city == place || name == place {
+                return Some(zone);
+            }
+        }
+        return None;
+    }
+
+    // the day being asked about, if there is one, along with its name if it has one like "Christmas" or "tomorrow"
+    fn parse_when(text : &str, today : NaiveDate) -> Option<(Option<String>, When)> {
+        if text.contains("today") {
+            return Some((Some(String::from("today")), When::Date(today)));
+        }
+        if text.contains("tomorrow") {
+            return Some((Some(String::from("tomorrow")), When::Date(today + Days::new(1))));
+        }
+        if text.contains("yesterday") {
+            return Some((Some(String::from("yesterday")), When::Date(today - Days::new(1))));
+        }
+        // the longest phrase wins, so "christmas eve" isn't taken for "christmas"
+        let holiday = HOLIDAYS.iter()
+            .filter_map(|h| h.phrases.iter().filter(|p| text.contains(*p)).map(|p| p.len()).max().map(|l| (l, h)))
+            .max_by_key(|(l, _)| *l);
+        if let Some((_, holiday)) = holiday {
+            return Some((Some(String::from(holiday.name)), When::Yearly(holiday.rule)));
+        }
+        let converted = replace_numbers_in_text(text, &Language::english(), 0.0);
+        let words : Vec<&str> = converted.split_whitespace().collect();
+        if let Some(i) = words.iter().position(|w| MONTHS.contains(w)) {
+            let month = MONTHS.iter().position(|m| *m == words[i]).unwrap_or(0) as u32 + 1;
+            // "march 3rd" or "the 3rd of march"
+            let day = words.get(i + 1).and_then(|w| day_number(w))
+                .or_else(|| if i >= 2 && words[i - 1] == "of" {day_number(words[i - 2])} else {None})
+                .or_else(|| if i >= 1 {day_number(words[i - 1])} else {None})?;
+            let rule = Rule::Fixed(month, day);
+            // a year like "2027", or "20 27" when it was said as "twenty twenty seven"
+            let number = |j : usize| words.get(j).and_then(|w| w.parse::<i32>().ok());
+            let year = match (number(i + 2), number(i + 3)) {
+                (Some(y), _) if y >= 1000 => {Some(y)},
+                (Some(century), Some(y)) if (10..100).contains(&century) && (0..100).contains(&y) => {Some(century * 100 + y)},
+                _ => {None},
+            };
+            return match year {
+                Some(year) => {Some((None, When::Date(rule.on(year)?)))},
+                None => {Some((None, When::Yearly(rule)))},
+            };
+        }
+        // "friday" means the next one, not today
+        if let Some((name, weekday)) = WEEKDAYS.iter().find(|(name, _)| words.contains(name)) {
+            let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+            let ahead = if ahead == 0 {7} else {ahead};
+            return Some((Some(capitalize(name)), When::Date(today + Days::new(ahead as u64))));
+        }
+        return None;
+    }
+
+    // picks the year for a yearly date
+    fn resolve(when : &When, today : NaiveDate, direction : Direction, text : &str) -> Option<NaiveDate> {
+        let rule = match when {
+            When::Date(date) => {return Some(*date)},
+            When::Yearly(rule) => {rule},
+        };
+        let year = if text.contains("next year") {
+            today.year() + 1
+        } else if text.contains("last year") {
+            today.year() - 1
+        } else {
+            today.year()
+        };
+        let mut date = rule.on(year);
+        // the 29th of February skips years, so look a few years out
+        for offset in 1..=8 {
+            match direction {
+                Direction::Next if date.is_none_or(|d| d < today) => {date = rule.on(year + offset)},
+                Direction::Last if date.is_none_or(|d| d > today) => {date = rule.on(year - offset)},
+                Direction::ThisYear if date.is_none() => {date = rule.on(year + offset)},
+                _ => {break},
+            }
+        }
+        return date;
+    }
+
+    fn tell_time(text : &str) -> String {
+        // the place is whatever comes after the last "in"
+        let place = text.rsplit_once(" in ").map(|(_, p)| p.trim_end_matches("right now").trim_end_matches(" now").trim());
+        let place = match place {
+            Some(p) if !p.is_empty() => {p},
+            _ => {
+                let now = Local::now();
+                return format!("It's {}.", spoken_time(now.time()));
+            },
+        };
+        let zone = match ClockCommand::find_zone(place) {
+            Some(z) => {z},
+            None => {return format!("I don't know what time zone {} is in.", place)},
+        };
+        let there = Utc::now().with_timezone(&zone);
+        let mut answer = format!("It's {} in {}", spoken_time(there.time()), capitalize_words(place));
+        let here = Local::now().date_naive();
+        if there.date_naive() > here {
+            answer.push_str(", tomorrow");
+        } else if there.date_naive() < here {
+            answer.push_str(", yesterday");
+        }
+        answer.push('.');
+        return answer;
+    }
+
+    fn answer(text : &str) -> String {
+        let today = Local::now().date_naive();
+        if ClockCommand::asks_time(text) && !text.contains("date") {
+            return ClockCommand::tell_time(text);
+        }
+        if text.contains("what year") {
+            return format!("It's {}.", today.year());
+        }
+        if text.contains("what month") {
+            return format!("It's {}.", capitalize(MONTHS[today.month0() as usize]));
+        }
+        let (name, when) = match ClockCommand::parse_when(text, today) {
+            Some(w) => {w},
+            None => {return format!("Today is {}.", spoken_date(today, true))},
+        };
+        // something that happens every year is taken to mean the next time it happens, unless a year was asked about
+        let direction = if ClockCommand::asks_until(text) {
+            Direction::Next
+        } else if ClockCommand::asks_since(text) || text.contains(" was ") {
+            Direction::Last
+        } else if text.contains("this year") || text.contains("next year") || text.contains("last year") {
+            Direction::ThisYear
+        } else {
+            Direction::Next
+        };
+        let date = match ClockCommand::resolve(&when, today, direction, text) {
+            Some(d) => {d},
+            None => {return String::from("I couldn't work out when that is.")},
+        };
+        let said_date = spoken_date(date, date.year() != today.year());
+        // "Christmas, on Friday, December 25th", or just the date if it doesn't have a name
+        let label = match &name {
+            Some(n) if n.to_lowercase() != said_date.split(',').next().unwrap_or("").to_lowercase() => {format!("{}, on {}", n, said_date)},
+            _ => {said_date.clone()},
+        };
+        let days = (date - today).num_days();
+        if ClockCommand::asks_until(text) {
+            return match days {
+                0 => {format!("{} is today.", capitalize(name.as_deref().unwrap_or("that")))},
+                1 => {format!("{} is tomorrow.", capitalize(name.as_deref().unwrap_or("that")))},
+                d if d > 0 => {format!("There are {} days until {}.", d, label)},
+                d => {format!("That was {} days ago.", -d)},
+            };
+        }
+        if ClockCommand::asks_since(text) {
+            return match days {
+                0 => {format!("{} is today.", capitalize(name.as_deref().unwrap_or("that")))},
+                -1 => {format!("{} was yesterday.", capitalize(name.as_deref().unwrap_or("that")))},
+                d if d < 0 => {format!("It's been {} days since {}.", -d, label)},
+                d => {format!("That's still {} days away.", d)},
+            };
+        }
+        if date == today {
+            return format!("Today is {}.", spoken_date(today, true));
+        }
+        let verb = if date < today {"was"} else {"is"};
+        return match name {
+            Some(n) => {format!("{} {} {}.", capitalize(&n), verb, said_date)},
+            None => {format!("That {} {}.", verb, said_date)},
+        };
+    }
+}
+impl Command for ClockCommand {
+    fn name(&self) -> String {
+        return String::from("Clock");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command tells you the time and date, here or in other places, and counts the days until or since a date.");
+    }
+    fn help(&self) -> String {
+        return String::from("Ask \"what time is it\", or \"what time is it in\" a city or country. Ask \"what's the date\" for today's date, or \"what day is\" a date or holiday, like \"what day is Christmas this year\". Ask \"how many days until\" or \"how many days since\" a date or holiday to count the days.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("time"), String::from("date"), String::from("time zones"), String::from("calendar")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("what time is it"), String::from("what time is it in Tokyo"), String::from("what's the date"),
+            String::from("what day is Christmas this year"), String::from("how many days until March third")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        let words : Vec<&str> = text.split_whitespace().collect();
+        if ClockCommand::asks_time(&text) || words.contains(&"date") || text.contains("what day") || text.contains("what year is it")
+            || text.contains("what month is it") {
+            return true;
+        }
+        // counting days, or "when is", only if there's a day to count to
+        let counting = text.contains("how many days") || text.contains("how long") || text.contains("when is") || text.contains("when was");
+        return counting && ClockCommand::parse_when(&text, Local::now().date_naive()).is_some();
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        speak.send(SpeakMessage::Say(ClockCommand::answer(&text))).unwrap();
+        return CommandResult::Done;
+    }
+}
+
+// a day of the month from a word like "3", "3rd", or "twenty third" after it's been turned into digits
+fn day_number(word : &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c : char| c.is_alphabetic());
+    return digits.parse::<u32>().ok().filter(|d| (1..=31).contains(d));
+}
+
+fn ordinal(n : u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => {"th"},
+        (1, _) => {"st"},
+        (2, _) => {"nd"},
+        (3, _) => {"rd"},
+        _ => {"th"},
+    };
+    return format!("{}{}", n, suffix);
+}
+
+// a date read out like "Monday, October 19th", with the year if it's wanted
+fn spoken_date(date : NaiveDate, year : bool) -> String {
+    let weekday = WEEKDAYS.iter().find(|(_, w)| *w == date.weekday()).map(|(n, _)| *n).unwrap_or("");
+    let mut said = format!("{}, {} {}", capitalize(weekday), capitalize(MONTHS[date.month0() as usize]), ordinal(date.day()));
+    if year {
+        said.push_str(&format!(", {}", date.year()));
+    }
+    return said;
+}
+
+// a time read out like "3:05 PM", "3 PM", "noon", or "midnight"
+pub fn spoken_time(time : NaiveTime) -> String {
+    let (hour, minute) = (time.hour(), time.minute());
+    match (hour, minute) {
+        (0, 0) => {return String::from("midnight")},
+        (12, 0) => {return String::from("noon")},
+        _ => {},
+    }
+    let half = if hour < 12 {"AM"} else {"PM"};
+    let hour = if hour % 12 == 0 {12} else {hour % 12};
+    if minute == 0 {
+        return format!("{} {}", hour, half);
+    }
+    return format!("{}:{:02} {}", hour, minute, half);
+}
+
+fn capitalize_words(text : &str) -> String {
+    return text.split_whitespace().map(capitalize).collect::<Vec<_>>().join(" ");
+}
@@ -0,0 +1,181 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use std::fs;
+use std::path::Path;
+use crate::commands::shell;
+use crate::http::HttpService;
+use toml;
+
+// what a custom command does before it responds
+enum Action {
+    Nothing,
+    Run(Vec<String>), // a program and its arguments, run directly without a shell
+    Http(String), // a URL to fetch
+}
+
+// a command defined in a TOML file instead of in Rust
+pub struct CustomCommand {
+    name : String,
+    desc : String,
+    help : String,
+    triggers : Vec<String>,
+    response : String,
+    action : Action,
+    uses_internet : bool,
+    http : HttpService,
+}
+impl CustomCommand {
+    // builds a command from the contents of a TOML file
+    pub fn parse(contents : &str, http : &HttpService) -> Result<CustomCommand, String> {
+        let table = contents.parse::<toml::Table>().map_err(|e| e.to_string())?;
+        let name = get_string(&table, "name")?.ok_or("missing \"name\"")?;
+        let triggers = get_string_list(&table, "triggers")?.ok_or("missing \"triggers\"")?;
+        if triggers.is_empty() {
+            return Err(String::from("\"triggers\" is empty"));
+        }
+        let action = match (get_string_list(&table, "run")?, get_string(&table, "http")?) {
+            (Some(_), Some(_)) => {return Err(String::from("only one of \"run\" and \"http\" can be given"))},
+            (Some(argv), None) => {
+                if argv.is_empty() {
+                    return Err(String::from("\"run\" is empty"));
+                }
+                Action::Run(argv)
+            },
+            (None, Some(url)) => {Action::Http(url)},
+            (None, None) => {Action::Nothing},
+        };
+        // without an action there's no output, so the response has to be given
+        let response = match get_string(&table, "response")? {
+            Some(r) => {r},
+            None if matches!(action, Action::Nothing) => {return Err(String::from("missing \"response\", which is needed when there's no \"run\" or \"http\""))},
+            None => {String::from("{output}")},
+        };
+        // anything fetching a URL always counts as using the internet, so offline mode can't be bypassed
+        let uses_internet = matches!(action, Action::Http(_)) || get_bool(&table, "uses_internet")?.unwrap_or(false);
+        return Ok(CustomCommand {
+            desc : get_string(&table, "description")?.unwrap_or(format!("A custom command named {}.", name)),
+            help : get_string(&table, "help")?.unwrap_or(format!("Say \"{}\".", triggers[0])),
+            response,
+            triggers : triggers.iter().map(|t| t.to_lowercase()).collect(),
+            name,
+            action,
+            uses_internet,
+            http : http.clone(),
+        });
+    }
+
+    // loads every .toml file in a directory as a command, skipping (and reporting) any that are broken
+    pub fn load_dir(dir : &str, http : &HttpService) -> Vec<CustomCommand> {
+        let mut commands = Vec::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => {e},
+            Err(_) => {return commands}, // no directory just means no custom commands
+        };
+        let mut paths : Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                match CustomCommand::load_file(&path, http) {
+                    Ok(c) => {
+                        println!("Loaded custom command \"{}\" from {}", c.name, path.display());
+                        commands.push(c);
+                    },
+                    Err(e) => {eprintln!("Error loading custom command {}: {}", path.display(), e);}
+                }
+            }
+        }
+        return commands;
+    }
+
+    fn load_file(path : &Path, http : &HttpService) -> Result<CustomCommand, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        return CustomCommand::parse(&contents, http);
+    }
+
+    // runs the action and gives back the text it produced, if any
+    fn run_action(&self) -> Result<String, String> {
+        match &self.action {
+            Action::Nothing => {return Ok(String::new())},
+            Action::Run(argv) => {
+                return shell::run_program(argv, shell::DEFAULT_TIMEOUT);
+            },
+            Action::Http(url) => {
+                let text = self.http.get(url, "text/plain").map_err(|e| e.describe("service"))?;
+                return Ok(text.trim().to_string());
+            },
+        }
+    }
+}
+impl Command for CustomCommand {
+    fn name(&self) -> String {
+        return self.name.clone();
+    }
+    fn desc(&self) -> String {
+        return self.desc.clone();
+    }
+    fn help(&self) -> String {
+        return self.help.clone();
+    }
+    fn uses_internet(&self) -> bool {
+        return self.uses_internet;
+    }
+    fn examples(&self) -> Vec<String> {
+        return self.triggers.clone();
+    }
+    fn runs_long(&self) -> bool {
+        return !matches!(self.action, Action::Nothing);
+    }
+    fn recognize(&self, text : String) -> bool {
+        return self.triggers.iter().any(|t| text.contains(t.as_str()));
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let output = match self.run_action() {
+            Ok(o) => {o},
+            Err(e) => {
+                speak.send(SpeakMessage::Say(e)).unwrap();
+                return CommandResult::Done;
+            }
+        };
+        let answer = self.response.replace("{output}", &output).replace("{text}", &text);
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+}
+
+// gets an optional string value out of a table
+pub fn get_string(table : &toml::Table, key : &str) -> Result<Option<String>, String> {
+    match table.get(key) {
+        Some(v) => {
+            let s = v.as_str().ok_or(format!("\"{}\" must be a string", key))?;
+            return Ok(Some(String::from(s)));
+        },
+        None => {return Ok(None)},
+    }
+}
+
+// gets an optional list of strings out of a table
+pub fn get_string_list(table : &toml::Table, key : &str) -> Result<Option<Vec<String>>, String> {
+    match table.get(key) {
+        Some(v) => {
+            let array = v.as_array().ok_or(format!("\"{}\" must be a list of strings", key))?;
+            let mut list = Vec::new();
+            for item in array {
+                list.push(String::from(item.as_str().ok_or(format!("\"{}\" must be a list of strings", key))?));
+            }
+            return Ok(Some(list));
+        },
+        None => {return Ok(None)},
+    }
+}
+
+// gets an optional true or false value out of a table
+pub fn get_bool(table : &toml::Table, key : &str) -> Result<Option<bool>, String> {
+    match table.get(key) {
+        Some(v) => {
+            let b = v.as_bool().ok_or(format!("\"{}\" must be true or false", key))?;
+            return Ok(Some(b));
+        },
+        None => {return Ok(None)},
+    }
+}
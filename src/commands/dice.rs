@@ -1,10 +1,505 @@
 use crate::commands::{Command, CommandResult};
 use std::sync::mpsc::{Sender};
 use crate::SpeakMessage;
-use text2num::{Language, text2digits};
-use rand;
+use crate::commands::spoken::spoken_list;
+use crate::storage::Storage;
+use text2num::{Language, replace_numbers_in_text};
+use rand::Rng;
+use json;
+use json::JsonValue;
 
-pub struct DiceCommand {}
+// where saved rolls and the roll history are kept
+const DICE_FILE : &str = "dice.json";
+// how many past rolls are remembered
+const HISTORY_SIZE : usize = 20;
+// how many past rolls are read out when asked
+const HISTORY_READ : usize = 3;
+
+// the most dice that can be rolled in one group, so nobody is stuck listening to a thousand rolls
+const MAX_DICE : u32 = 100;
+const MAX_SIDES : u32 = 1000;
+// past this many rolls, only the total is read out
+const MAX_LISTED : usize = 12;
+// how many times one die can keep exploding, since a two sided die could go on for a while
+const MAX_EXPLOSIONS : u32 = 20;
+// words that don't change the roll, and are left out before it's parsed
+const FILLER_WORDS : [&str; 9] = ["the", "please", "me", "for", "with", "at", "of", "some", "us"];
+
+// which dice in a group count towards the total
+#[derive(Clone, Copy)]
+enum Keep {
+    All,
+    Highest(u32),
+    Lowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+}
+
+// some number of the same kind of die, like "four d six drop the lowest"
+struct Group {
+    count : u32,
+    sides : u32,
+    keep : Keep,
+    exploding : bool, // a die that rolls its highest number is rolled again and added on
+    negative : bool,
+}
+
+// one part of a roll, added to or taken from the total
+enum Term {
+    Dice(Group),
+    Number(i64),
+}
+
+// the result of rolling one group
+struct Rolled {
+    rolls : Vec<u32>, // each die's total, with anything it rolled from exploding added on
+    kept : Vec<bool>, // whether each die counts towards the total
+    exploded : usize, // how many extra rolls there were from exploding
+}
+
+// reads a roll one word at a time
+struct Parser {
+    words : Vec<String>,
+    pos : usize,
+}
+impl Parser {
+    fn new(text : &str) -> Parser {
+        // "+" and "-" need to be words of their own, and numbers need to be digits
+        let spaced = text.replace('+', " plus ").replace('-', " minus ");
+        let converted = replace_numbers_in_text(&spaced, &Language::english(), 0.0);
+        // split up things like "2d6" so the numbers and the "d" are separate
+        let mut split = String::new();
+        let mut last : Option<char> = None;
+        for c in converted.chars() {
+            if last.is_some_and(|l| l.is_ascii_digit() != c.is_ascii_digit() && l.is_alphanumeric() && c.is_alphanumeric()) {
+                split.push(' ');
+            }
+            split.push(c);
+            last = Some(c);
+        }
+        let words = split.split_whitespace()
+            .map(|w| w.trim_matches(|c : char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|w| !w.is_empty() && !FILLER_WORDS.contains(&w.as_str()))
+            .collect();
+        return Parser{words, pos : 0};
+    }
+
+    fn peek(&self) -> Option<&str> {
+        return self.words.get(self.pos).map(|w| w.as_str());
+    }
+
+    fn peek_number(&self) -> Option<u32> {
+        return self.peek().and_then(|w| w.parse().ok());
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let word = self.words.get(self.pos).cloned();
+        self.pos += 1;
+        return word;
+    }
+
+    // parses the whole roll, giving back something to say if it couldn't
+    fn parse(&mut self) -> Result<Vec<Term>, String> {
+        let mut terms = Vec::new();
+        let mut negative = false;
+        let mut exploding = false; // "exploding" said before the dice it goes with
+        let mut advantage : Option<bool> = None;
+        while let Some(word) = self.peek() {
+            match word {
+                "plus" | "and" | "add" => {
+                    negative = false;
+                    self.pos += 1;
+                },
+                "minus" | "less" | "subtract" => {
+                    negative = true;
+                    self.pos += 1;
+                },
+                "advantage" => {
+                    advantage = Some(true);
+                    self.pos += 1;
+                },
+                "disadvantage" => {
+                    advantage = Some(false);
+                    self.pos += 1;
+                },
+                "exploding" | "explode" | "explodes" | "exploded" => {
+                    self.pos += 1;
+                    match terms.iter_mut().rev().find_map(|t| if let Term::Dice(g) = t {Some(g)} else {None}) {
+                        Some(group) => {group.exploding = true},
+                        None => {exploding = true},
+                    }
+                },
+                "keep" | "drop" | "dropping" | "keeping" => {
+                    let keep = self.parse_keep()?;
+                    match terms.last_mut() {
+                        Some(Term::Dice(group)) => {group.keep = keep},
+                        _ => {return Err(String::from("I need to know which dice to keep or drop from."))},
+                    }
+                },
+                _ => {
+                    let mut term = self.parse_term()?;
+                    match &mut term {
+                        Term::Dice(group) => {
+                            group.negative = negative;
+                            group.exploding |= exploding;
+                            exploding = false;
+                        },
+                        Term::Number(n) => {
+                            if negative {
+                                *n = -*n;
+                            }
+                        },
+                    }
+                    negative = false;
+                    terms.push(term);
+                },
+            }
+        }
+        if !terms.iter().any(|t| matches!(t, Term::Dice(_))) {
+            return Err(String::from("I didn't hear any dice to roll. Say something like \"roll two d six plus three\"."));
+        }
+        if let Some(higher) = advantage {
+            // advantage means rolling the die twice and taking the better one
+            if let Some(group) = terms.iter_mut().find_map(|t| if let Term::Dice(g) = t {Some(g)} else {None}) {
+                if group.count != 1 || !matches!(group.keep, Keep::All) {
+                    return Err(String::from("Advantage and disadvantage only work with a single die."));
+                }
+                group.count = 2;
+                group.keep = if higher {Keep::Highest(1)} else {Keep::Lowest(1)};
+            }
+        }
+        return Ok(terms);
+    }
+
+    // a group of dice like "two d six", "a d twenty", or "three six sided dice", or just a number
+    fn parse_term(&mut self) -> Result<Term, String> {
+        let first = self.next().unwrap_or_default();
+        let mut count = match first.as_str() {
+            // "a hundred d six" is a hundred dice, not one
+            "a" | "an" if self.peek_number().is_some() && self.words.get(self.pos + 1).is_some_and(|w| w != "sided") => {
+                self.next().and_then(|w| w.parse().ok())
+            },
+            "a" | "an" => {Some(1)},
+            "d" | "dee" | "die" | "dice" => {
+                self.pos -= 1;
+                None
+            },
+            w => {Some(w.parse::<u32>().or(Err(format!("I couldn't understand \"{}\" in that roll.", w)))?)},
+        };
+        // "three exploding d six"
+        let exploding = matches!(self.peek(), Some("exploding") | Some("explode") | Some("explodes") | Some("exploded"));
+        if exploding {
+            self.pos += 1;
+        }
+        let mut sides = None;
+        if self.peek() == Some("sided") {
+            // the number was the sides, like "six sided die"
+            self.pos += 1;
+            sides = count;
+            count = None;
+        } else if self.peek_number().is_some() && self.words.get(self.pos + 1).is_some_and(|w| w == "sided") {
+            sides = self.peek_number();
+            self.pos += 2;
+        }
+        match self.peek() {
+            Some("d") | Some("dee") => {
+                self.pos += 1;
+                if sides.is_some() {
+                    return Err(String::from("I heard the number of sides twice."));
+                }
+                sides = match self.next().as_deref() {
+                    Some("percent") | Some("percentile") => {Some(100)},
+                    Some(w) => {w.parse().ok()},
+                    None => {None},
+                };
+                if sides.is_none() {
+                    return Err(String::from("I couldn't make out how many sides the dice have."));
+                }
+            },
+            Some("die") | Some("dice") => {
+                self.pos += 1;
+            },
+            _ => {
+                if sides.is_none() {
+                    if first == "a" || first == "an" || exploding {
+                        return Err(String::from("I couldn't make out what kind of die to roll."));
+                    }
+                    return Ok(Term::Number(count.unwrap_or(0) as i64));
+                }
+            },
+        }
+        let count = count.unwrap_or(1);
+        let sides = sides.unwrap_or(6);
+        if count == 0 {
+            return Err(String::from("I need to roll at least one die."));
+        }
+        if sides == 0 {
+            return Err(String::from("A die needs at least one side."));
+        }
+        if count > MAX_DICE {
+            return Err(format!("That's too many dice. I can roll up to {} at a time.", MAX_DICE));
+        }
+        if sides > MAX_SIDES {
+            return Err(format!("That's too many sides. I can roll dice with up to {} sides.", MAX_SIDES));
+        }
+        return Ok(Term::Dice(Group{count, sides, keep : Keep::All, exploding, negative : false}));
+    }
+
+    // "keep the highest three", "drop the lowest", and so on
+    fn parse_keep(&mut self) -> Result<Keep, String> {
+        let keeping = matches!(self.next().as_deref(), Some("keep") | Some("keeping"));
+        let mut highest = keeping; // "keep three" means the highest, "drop one" means the lowest
+        if let Some(word) = self.peek() {
+            match word {
+                "highest" | "best" | "top" | "high" => {
+                    highest = true;
+                    self.pos += 1;
+                },
+                "lowest" | "worst" | "bottom" | "low" => {
+                    highest = false;
+                    self.pos += 1;
+                },
+                _ => {},
+            }
+        }
+        let number = match self.peek_number() {
+            Some(n) => {
+                self.pos += 1;
+                n
+            },
+            None => {1},
+        };
+        match (keeping, highest) {
+            (true, true) => {return Ok(Keep::Highest(number))},
+            (true, false) => {return Ok(Keep::Lowest(number))},
+            (false, true) => {return Ok(Keep::DropHighest(number))},
+            (false, false) => {return Ok(Keep::DropLowest(number))},
+        }
+    }
+}
+
+impl Group {
+    fn roll(&self) -> Rolled {
+        let mut rng = rand::thread_rng();
+        let mut rolls = Vec::new();
+        let mut exploded = 0;
+        for _ in 0..self.count {
+            let mut roll = rng.gen_range(1..=self.sides);
+            let mut die = roll;
+            // a one sided die would explode forever
+            let mut explosions = 0;
+            while self.exploding && self.sides > 1 && roll == self.sides && explosions < MAX_EXPLOSIONS {
+                roll = rng.gen_range(1..=self.sides);
+                die += roll;
+                exploded += 1;
+                explosions += 1;
+            }
+            rolls.push(die);
+        }
+        // keeping and dropping go by dice, not by rolls, so an explosion can't push a die out
+        let total = self.count;
+        // how many to keep, and whether they're the highest or lowest
+        let (keep, highest) = match self.keep {
+            Keep::All => {(total, true)},
+            Keep::Highest(n) => {(n.min(total), true)},
+            Keep::Lowest(n) => {(n.min(total), false)},
+            Keep::DropHighest(n) => {(total.saturating_sub(n), false)},
+            Keep::DropLowest(n) => {(total.saturating_sub(n), true)},
+        };
+        let mut order : Vec<usize> = (0..rolls.len()).collect();
+        order.sort_by_key(|i| rolls[*i]);
+        if highest {
+            order.reverse();
+        }
+        let mut kept = vec![false; rolls.len()];
+        for i in order.iter().take(keep as usize) {
+            kept[*i] = true;
+        }
+        return Rolled{rolls, kept, exploded};
+    }
+}
+
+impl Rolled {
+    fn total(&self) -> i64 {
+        return self.rolls.iter().zip(&self.kept).filter(|(_, k)| **k).map(|(r, _)| *r as i64).sum();
+    }
+}
+
+fn spoken_number(n : i64) -> String {
+    if n < 0 {
+        return format!("minus {}", -n);
+    }
+    return n.to_string();
+}
+
+// a roll from earlier, for answering "what did I roll last"
+struct PastRoll {
+    roll : String, // what was asked for, like "2 d 6 plus 3"
+    total : i64,
+}
+
+pub struct DiceCommand {
+    macros : Vec<(String, String)>, // saved rolls, by name, like ("sword attack", "1 d 8 plus 3")
+    history : Vec<PastRoll>, // oldest first
+    storage : Storage,
+}
+impl DiceCommand {
+    pub fn new(storage : Storage) -> DiceCommand {
+        let mut macros = Vec::new();
+        let mut history = Vec::new();
+        if let Some(saved) = storage.load(DICE_FILE) {
+            for (name, roll) in saved["macros"].entries() {
+                if let Some(roll) = roll.as_str() {
+                    macros.push((String::from(name), String::from(roll)));
+                }
+            }
+            for past in saved["history"].members() {
+                if let (Some(roll), Some(total)) = (past["roll"].as_str(), past["total"].as_i64()) {
+                    history.push(PastRoll{roll : String::from(roll), total});
+                }
+            }
+        }
+        return DiceCommand{macros, history, storage};
+    }
+
+    fn save(&self) {
+        let mut macros = JsonValue::new_object();
+        for (name, roll) in &self.macros {
+            macros[name.as_str()] = roll.clone().into();
+        }
+        let history : Vec<JsonValue> = self.history.iter().map(|p| json::object!{
+            roll : p.roll.clone(),
+            total : p.total,
+        }).collect();
+        let saved = json::object!{
+            macros : macros,
+            history : history,
+        };
+        if let Err(e) = self.storage.save(DICE_FILE, &saved) {
+            eprintln!("Error saving dice rolls: {}", e);
+        }
+    }
+
+    // the saved roll whose name the text starts with, along with whatever was said after the name
+    fn find_macro<'a>(&self, text : &'a str) -> Option<(usize, &'a str)> {
+        let text = text.trim().trim_start_matches("my ").trim_start_matches("the ");
+        // the longest name wins, so "sword attack" doesn't get taken for "sword"
+        let mut best : Option<usize> = None;
+        for (i, (name, _)) in self.macros.iter().enumerate() {
+            let fits = text == name || text.starts_with(&format!("{} ", name));
+            if fits && best.is_none_or(|b| self.macros[b].0.len() < name.len()) {
+                best = Some(i);
+            }
+        }
+        let index = best?;
+        let rest = text[self.macros[index].0.len()..].trim_start().trim_start_matches("roll");
+        return Some((index, rest));
+    }
+
+    // picks out requests like "save my sword attack as one d eight plus three", giving back the name and the roll
+    fn parse_save(text : &str) -> Option<(String, String)> {
+        let rest = text.trim().strip_prefix("save ")?;
+        let (name, roll) = rest.split_once(" as ")?;
+        let mut name = name.trim();
+        for prefix in ["a roll called ", "a roll named ", "the roll ", "roll ", "my ", "a "] {
+            name = name.strip_prefix(prefix).unwrap_or(name);
+        }
+        let name = name.strip_suffix(" roll").unwrap_or(name).trim();
+        let roll = roll.trim().strip_prefix("roll ").unwrap_or(roll.trim());
+        if name.is_empty() || Parser::new(roll).parse().is_err() {
+            return None;
+        }
+        return Some((String::from(name), Parser::new(roll).words.join(" ")));
+    }
+
+    // picks out requests like "forget my sword attack roll", giving back which saved roll it is
+    fn parse_forget(&self, text : &str) -> Option<usize> {
+        let rest = ["forget ", "delete ", "remove "].iter().find_map(|w| text.trim().strip_prefix(w))?;
+        let rest = rest.trim_start_matches("my ").trim_start_matches("the ").trim_start_matches("roll ");
+        let rest = rest.strip_suffix(" roll").unwrap_or(rest);
+        return self.macros.iter().position(|(name, _)| name == rest);
+    }
+
+    fn asks_history(text : &str) -> bool {
+        return text.contains("what did i roll") || text.contains("last roll") || text.contains("roll history")
+            || text.contains("previous roll") || text.contains("rolled last");
+    }
+
+    fn asks_macros(text : &str) -> bool {
+        return text.contains("saved rolls") || text.contains("rolls do i have") || text.contains("roll macros");
+    }
+
+    // rolls and remembers the roll
+    fn roll(&mut self, roll : &str) -> String {
+        let (answer, total) = match Parser::new(roll).parse() {
+            Ok(terms) => {DiceCommand::describe(&terms)},
+            Err(e) => {return e},
+        };
+        self.history.push(PastRoll{roll : Parser::new(roll).words.join(" "), total});
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+        self.save();
+        return answer;
+    }
+
+    // rolls everything and describes how it went, along with the total
+    fn describe(terms : &[Term]) -> (String, i64) {
+        let groups = terms.iter().filter(|t| matches!(t, Term::Dice(_))).count();
+        let mut sentences = Vec::new();
+        let mut total : i64 = 0;
+        let mut counted = 0; // how many numbers went into the total
+        let mut modifier : i64 = 0;
+        for term in terms {
+            match term {
+                Term::Dice(group) => {
+                    let rolled = group.roll();
+                    let rolls : Vec<String> = if rolled.rolls.len() > MAX_LISTED {
+                        vec![format!("{} dice", rolled.rolls.len())]
+                    } else {
+                        rolled.rolls.iter().map(|r| r.to_string()).collect()
+                    };
+                    let mut sentence = if groups > 1 {
+                        format!("For {}{} d {}, I rolled {}", if group.negative {"minus "} else {""},
+                            group.count, group.sides, spoken_list(&rolls))
+                    } else {
+                        format!("I rolled {}", spoken_list(&rolls))
+                    };
+                    if rolled.exploded > 0 {
+                        sentence.push_str(&format!(", with {} extra roll{} from exploding added in", rolled.exploded,
+                            if rolled.exploded == 1 {""} else {"s"}));
+                    }
+                    let dropped : Vec<String> = rolled.rolls.iter().zip(&rolled.kept)
+                        .filter(|(_, k)| !**k).map(|(r, _)| r.to_string()).collect();
+                    let kept : Vec<String> = rolled.rolls.iter().zip(&rolled.kept)
+                        .filter(|(_, k)| **k).map(|(r, _)| r.to_string()).collect();
+                    let listed = rolled.rolls.len() <= MAX_LISTED;
+                    if listed && !dropped.is_empty() && kept.len() == 1 {
+                        sentence.push_str(&format!(", keeping {}", kept[0]));
+                    } else if listed && !dropped.is_empty() {
+                        sentence.push_str(&format!(", dropping {}", spoken_list(&dropped)));
+                    }
+                    sentence.push('.');
+                    sentences.push(sentence);
+                    counted += rolled.kept.iter().filter(|k| **k).count();
+                    total += if group.negative {-rolled.total()} else {rolled.total()};
+                },
+                Term::Number(n) => {
+                    modifier += n;
+                    counted += 1;
+                },
+            }
+        }
+        if modifier != 0 {
+            sentences.push(format!("{} {}.", if modifier > 0 {"Plus"} else {"Minus"}, modifier.abs()));
+        }
+        total += modifier;
+        if counted > 1 {
+            sentences.push(format!("That's a total of {}.", spoken_number(total)));
+        }
+        return (sentences.join(" "), total);
+    }
+}
 impl Command for DiceCommand {
     fn name(&self) -> String {
         return String::from("Dice Command");
@@ -13,59 +508,71 @@ impl Command for DiceCommand {
         return String::from("This command rolls dice.");
     }
     fn help(&self) -> String {
-        return String::from("Say \"Roll\" followed by a type and number of dice in the number D number format.");
+        return String::from("Say \"Roll\" followed by a number and type of dice in the number D number format, like \"roll two d six\", or \"roll a d twenty\". You can add or subtract numbers and other dice, like \"roll two d eight plus one d six plus three\". Say \"keep the highest\" or \"drop the lowest\" followed by a number to only count some of the dice, \"with advantage\" or \"with disadvantage\" to roll a die twice and take the better or worse one, and \"exploding\" to roll again whenever a die rolls its highest number. To save a roll, say something like \"save my sword attack as one d eight plus three\", then say \"roll my sword attack\" to roll it. Ask \"what are my saved rolls\" to hear them, or \"what did I roll last\" to hear your last few rolls.");
     }
     fn uses_internet(&self) -> bool {
         return false;
     }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("dice"), String::from("roll"), String::from("die")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("roll two d six plus three"), String::from("roll a d twenty with advantage"),
+            String::from("roll four d six drop the lowest"), String::from("roll three exploding d six"),
+            String::from("save my sword attack as one d eight plus three"), String::from("what did I roll last")];
+    }
     fn recognize(&self, text : String) -> bool {
-        return text.contains("roll ") || text.contains("role ");
+        return text.contains("roll ") || text.contains("role ") || DiceCommand::asks_history(&text)
+            || DiceCommand::asks_macros(&text) || DiceCommand::parse_save(&text).is_some() || self.parse_forget(&text).is_some();
     }
     fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
-        // parse out the numbers given
-        let (_, dice) = if text.contains("roll") {
-            text.split_once("roll").unwrap()
-        } else {
-            text.split_once("role").unwrap()
-        };
-        if !dice.contains("d") {
-            speak.send(SpeakMessage::Say(String::from("Make sure to say \"Roll\" followed by a type and number of dice in the number D number format."))).unwrap();
-            return CommandResult::Done;
-        }
-        let (str_num, str_size) = dice.split_once("d").unwrap();
-        println!("numbers: \"{}\", \"{}\"", str_num, str_size);
-        // convert the numbers into u32
-        let en = Language::english();
-        let num : u32 = match text2digits(str_num, &en) {
-            Ok(n) => n,
-            Err(_) => {
-                speak.send(SpeakMessage::Say(String::from("I couldn't make out the first number. Please try again."))).unwrap();
-                return CommandResult::Done;
-            },
-        }.parse().unwrap();
-        let size : u32 = match text2digits(str_size, &en) {
-            Ok(n) => n,
-            Err(_) => {
-                speak.send(SpeakMessage::Say(String::from("I couldn't make out the second number. Please try again."))).unwrap();
-                return CommandResult::Done;
-            },
-        }.parse().unwrap();
-        // roll the dice and format the output
-        let mut answer = String::from("I rolled: ");
-        for i in 1..=num {
-            let roll = (rand::random::<u32>() % size) + 1;
-            if num == 1 {
-                answer.push_str(&format!("{}.", roll));
+        let answer = if DiceCommand::asks_history(&text) {
+            if self.history.is_empty() {
+                String::from("You haven't rolled anything yet.")
+            } else {
+                let past : Vec<String> = self.history.iter().rev().take(HISTORY_READ)
+                    .map(|p| format!("{} and got {}", p.roll, spoken_number(p.total)))
+                    .collect();
+                format!("You rolled {}.", spoken_list(&past))
             }
-            else if i == num {
-                answer.push_str(&format!("and {}.", roll));
+        } else if DiceCommand::asks_macros(&text) {
+            if self.macros.is_empty() {
+                String::from("You don't have any saved rolls.")
             } else {
-                answer.push_str(&format!("{}, ", roll));
+                let macros : Vec<String> = self.macros.iter().map(|(name, roll)| format!("{}, which is {}", name, roll)).collect();
+                format!("Your saved rolls are {}.", spoken_list(&macros))
             }
-        }
+        } else if let Some((name, roll)) = DiceCommand::parse_save(&text) {
+            let answer = format!("Okay, say \"roll {}\" to roll {}.", name, roll);
+            match self.macros.iter_mut().find(|(n, _)| *n == name) {
+                Some(existing) => {existing.1 = roll},
+                None => {self.macros.push((name, roll))},
+            }
+            self.save();
+            answer
+        } else if let Some(index) = self.parse_forget(&text) {
+            let (name, _) = self.macros.remove(index);
+            self.save();
+            format!("Okay, I forgot your {} roll.", name)
+        } else {
+            let dice = match text.split_once("roll ").or_else(|| text.split_once("role ")) {
+                Some((_, d)) => {d},
+                None => {&text},
+            };
+            // a saved roll can have more added on, like "roll my sword attack with advantage"
+            match self.find_macro(dice) {
+                Some((index, rest)) => {
+                    let dice = format!("{} {}", self.macros[index].1, rest);
+                    self.roll(&dice)
+                },
+                None if dice.trim().starts_with("my ") => {
+                    format!("You don't have a saved roll called {}.", dice.trim().trim_start_matches("my "))
+                },
+                None => {self.roll(dice)},
+            }
+        };
+        println!("{}", answer);
         speak.send(SpeakMessage::Say(answer)).unwrap();
         return CommandResult::Done;
     }
 }
-
-
@@ -1,28 +1,77 @@
-use crate::commands::{Command, CommandResult};
+use crate::commands::{Command, CommandResult, SessionState};
 use std::sync::mpsc::{Sender};
 use crate::SpeakMessage;
 
-enum State {
-    AskForCommand,
-    GiveHelp,
+// how close a spoken name has to be to a command's name to count as a match
+const MATCH_THRESHOLD : f32 = 0.75;
+
+// everything the help command needs to know about another command
+struct HelpEntry {
+    name : String,
+    names : Vec<String>, // lowercased name and aliases, without the word "command"
+    desc : String,
+    help : String,
+    examples : Vec<String>,
+    net : bool,
 }
 
 pub struct HelpCommand {
-    list : Vec<(String, String, bool)>,
-    state : State,
+    list : Vec<HelpEntry>,
 }
 impl HelpCommand {
     pub fn new(commands : &Vec<Box<dyn Command>>) -> HelpCommand {
         let mut list = Vec::new();
         for command in commands {
-            let mut lower = command.name();
-            lower.make_ascii_lowercase();
-            list.push((lower, command.help(), command.uses_internet()));
+            let mut names = vec![normalize(&command.name())];
+            for alias in command.aliases() {
+                names.push(normalize(&alias));
+            }
+            list.push(HelpEntry {
+                name : command.name(),
+                names,
+                desc : command.desc(),
+                help : command.help(),
+                examples : command.examples(),
+                net : command.uses_internet(),
+            });
         }
-        return HelpCommand{
-            list,
-            state : State::AskForCommand,
-        };
+        return HelpCommand{list};
+    }
+
+    // finds the command whose name or alias is closest to something in the text
+    fn find(&self, text : &str) -> Option<&HelpEntry> {
+        let text = normalize(text);
+        let mut best : Option<(&HelpEntry, f32)> = None;
+        for entry in &self.list {
+            for name in &entry.names {
+                let score = match_score(&text, name);
+                if score >= MATCH_THRESHOLD && best.map_or(true, |(_, s)| score > s) {
+                    best = Some((entry, score));
+                }
+            }
+        }
+        return best.map(|(entry, _)| entry);
+    }
+
+    // the full help text for one command
+    fn help_text(entry : &HelpEntry) -> String {
+        let mut text = entry.help.clone();
+        if !entry.examples.is_empty() {
+            text.push_str(&format!(" For example, you could say \"{}\".", entry.examples.join("\", or \"")));
+        }
+        let does_it = if entry.net {String::from("does")} else {String::from("does not")};
+        text.push_str(&format!(" This command {} require the internet.", does_it));
+        return text;
+    }
+
+    // a rundown of every command using their descriptions
+    fn list_text(&self) -> String {
+        let mut text = String::from("Here's what I can do.");
+        for entry in &self.list {
+            text.push_str(&format!(" {}: {}", entry.name, entry.desc));
+        }
+        text.push_str(" Say \"help\" and the name of a command to learn more about it.");
+        return text;
     }
 }
 impl Command for HelpCommand {
@@ -33,35 +82,102 @@ impl Command for HelpCommand {
         return String::from("This command gives help information for any of the available commands.");
     }
     fn help(&self) -> String {
-        return String::from("Say \"Help\" and then supply the name of a command when prompted.");
+        return String::from("Say \"Help\" and then supply the name of a command when prompted, or ask what I can do to hear a list of commands.");
     }
     fn uses_internet(&self) -> bool {
         return false;
     }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("commands")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("help with the weather"), String::from("what can you do")];
+    }
     fn recognize(&self, text : String) -> bool {
-        return text.contains("help");
+        return text.contains("help") || is_list_request(&text);
     }
     fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
-        match self.state {
-            State::AskForCommand => {
-                speak.send(SpeakMessage::Say(String::from("Which command would you like help with?"))).unwrap();
-                self.state = State::GiveHelp;
-                return CommandResult::Continue;
+        if is_list_request(&text) {
+            speak.send(SpeakMessage::Say(self.list_text())).unwrap();
+            return CommandResult::Done;
+        }
+        // they might have named the command already, like "help with the weather"
+        let (_, rest) = text.split_once("help").unwrap_or(("", &text));
+        if let Some(entry) = self.find(rest) {
+            speak.send(SpeakMessage::Say(HelpCommand::help_text(entry))).unwrap();
+            return CommandResult::Done;
+        }
+        speak.send(SpeakMessage::Say(String::from("Which command would you like help with?"))).unwrap();
+        // nothing needs to be remembered between asking and answering
+        return CommandResult::Continue(Box::new(()));
+    }
+    fn resume(&mut self, _state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        if is_list_request(&text) {
+            speak.send(SpeakMessage::Say(self.list_text())).unwrap();
+            return CommandResult::Done;
+        }
+        // say the help text for whatever command they say
+        match self.find(&text) {
+            Some(entry) => {
+                speak.send(SpeakMessage::Say(HelpCommand::help_text(entry))).unwrap();
             },
-            State::GiveHelp => {
-                // say the help text for whatever command they say
-                for (name, help, net) in &self.list {
-                    if text.contains(name) {
-                        let does_it = if *net {String::from("does")} else {String::from("does not")};
-                        speak.send(SpeakMessage::Say(format!("{} This command {} require the internet.", help, does_it))).unwrap();
-                        return CommandResult::Done;
-                    }
-                }
+            None => {
                 speak.send(SpeakMessage::Say(format!("I couldn't find a command named {}, please try again.", text))).unwrap();
-                self.state = State::AskForCommand;
-                return CommandResult::Done;
             }
         }
+        return CommandResult::Done;
+    }
+}
+
+// whether the text is asking for all of the commands
+fn is_list_request(text : &str) -> bool {
+    return text.contains("what can you do") || text.contains("list commands") || text.contains("list the commands")
+        || text.contains("what commands");
+}
+
+// lowercases and drops the word "command" so "dice command" and "dice" are the same name
+fn normalize(text : &str) -> String {
+    return text.to_lowercase()
+        .split_whitespace()
+        .filter(|w| *w != "command" && *w != "commands" && *w != "the")
+        .collect::<Vec<_>>()
+        .join(" ");
+}
+
+// how well a name matches the text from 0 to 1, comparing the name to every run of words of the same length
+fn match_score(text : &str, name : &str) -> f32 {
+    if name.is_empty() {
+        return 0.0;
+    }
+    if text.contains(name) {
+        return 1.0;
+    }
+    let words : Vec<&str> = text.split_whitespace().collect();
+    let len = name.split_whitespace().count();
+    let mut best = 0.0;
+    for window in words.windows(len) {
+        let candidate = window.join(" ");
+        let longest = candidate.chars().count().max(name.chars().count());
+        let score = 1.0 - (edit_distance(&candidate, name) as f32 / longest as f32);
+        if score > best {
+            best = score;
+        }
+    }
+    return best;
+}
 
+// the number of single character changes it takes to turn one string into the other
+fn edit_distance(a : &str, b : &str) -> usize {
+    let a : Vec<char> = a.chars().collect();
+    let b : Vec<char> = b.chars().collect();
+    let mut prev : Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i-1] == b[j-1] {0} else {1};
+            current[j] = (prev[j] + 1).min(current[j-1] + 1).min(prev[j-1] + cost);
+        }
+        prev = current;
     }
+    return prev[b.len()];
 }
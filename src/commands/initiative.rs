@@ -0,0 +1,234 @@
+use crate::commands::{Command, CommandResult, SessionState};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use crate::storage::Storage;
+use text2num::{Language, replace_numbers_in_text};
+use rand::Rng;
+use json;
+use json::JsonValue;
+
+// where the current fight is saved, so it survives a restart partway through
+const INITIATIVE_FILE : &str = "initiative.json";
+// answers that mean everyone has been added
+const DONE_PHRASES : [&str; 7] = ["done", "that's everyone", "that is everyone", "that's it", "that's all", "no one else", "nobody else"];
+// words left out of names, so "the goblin rolled fifteen" is just "goblin"
+const FILLER_WORDS : [&str; 6] = ["the", "and", "with", "has", "rolled", "got"];
+// words that end the fight, like "end initiative" or "clear the initiative", matched as whole words so names like "Brendan" don't
+const END_WORDS : [&str; 5] = ["end", "clear", "stop", "finish", "over"];
+
+// someone in the fight and their place in the order
+struct Combatant {
+    name : String,
+    roll : i64,
+}
+
+// keeps track of turn order in a tabletop fight
+pub struct InitiativeCommand {
+    order : Vec<Combatant>, // highest roll first
+    turn : usize, // index into order
+    round : u32,
+    storage : Storage,
+}
+impl InitiativeCommand {
+    pub fn new(storage : Storage) -> InitiativeCommand {
+        let mut command = InitiativeCommand{order : Vec::new(), turn : 0, round : 1, storage};
+        if let Some(saved) = command.storage.load(INITIATIVE_FILE) {
+            for entry in saved["order"].members() {
+                if let (Some(name), Some(roll)) = (entry["name"].as_str(), entry["roll"].as_i64()) {
+                    command.order.push(Combatant{name : String::from(name), roll});
+                }
+            }
+            command.turn = saved["turn"].as_usize().unwrap_or(0).min(command.order.len().saturating_sub(1));
+            command.round = saved["round"].as_u32().unwrap_or(1);
+        }
+        return command;
+    }
+
+    fn save(&self) {
+        let order : Vec<JsonValue> = self.order.iter().map(|c| json::object!{
+            name : c.name.clone(),
+            roll : c.roll,
+        }).collect();
+        let saved = json::object!{
+            order : order,
+            turn : self.turn,
+            round : self.round,
+        };
+        if let Err(e) = self.storage.save(INITIATIVE_FILE, &saved) {
+            eprintln!("Error saving initiative: {}", e);
+        }
+    }
+
+    // the words of a name and roll, with numbers written as digits and filler like "the" taken out
+    fn clean(text : &str) -> String {
+        let converted = replace_numbers_in_text(text, &Language::english(), 0.0);
+        return converted.split_whitespace().filter(|w| !FILLER_WORDS.contains(w)).collect::<Vec<&str>>().join(" ");
+    }
+
+    // reads something like "goblin fifteen" or "goblin plus two", rolling a d20 for them if no roll was given
+    // gives back who it was, and what to say about it
+    fn parse_entry(text : &str) -> Option<(Combatant, String)> {
+        let cleaned = InitiativeCommand::clean(text);
+        let mut words : Vec<&str> = cleaned.split_whitespace().collect();
+        let modifier = words.iter().position(|w| *w == "plus" || *w == "minus");
+        if let Some(i) = modifier {
+            let bonus : i64 = words.get(i + 1)?.parse().ok()?;
+            let bonus = if words[i] == "minus" {-bonus} else {bonus};
+            let name = words[..i].join(" ");
+            if name.is_empty() {
+                return None;
+            }
+            let die = rand::thread_rng().gen_range(1..=20);
+            let said = format!("{} rolled {} {} {}, for {}.", name, die, words[i], bonus.abs(), die + bonus);
+            return Some((Combatant{name, roll : die + bonus}, said));
+        }
+        let roll = words.last().and_then(|w| w.parse::<i64>().ok());
+        if roll.is_some() {
+            words.pop();
+        }
+        let name = words.join(" ");
+        if name.is_empty() {
+            return None;
+        }
+        match roll {
+            Some(roll) => {
+                let said = format!("{}, {}.", name, roll);
+                return Some((Combatant{name, roll}, said));
+            },
+            None => {
+                let die = rand::thread_rng().gen_range(1..=20);
+                let said = format!("{} rolled {}.", name, die);
+                return Some((Combatant{name, roll : die}, said));
+            }
+        }
+    }
+
+    // adds someone to the order after the others with the same roll
+    fn insert(&mut self, combatant : Combatant) {
+        let index = self.order.iter().position(|c| c.roll < combatant.roll).unwrap_or(self.order.len());
+        if index <= self.turn && !self.order.is_empty() {
+            // whoever's turn it is keeps it
+            self.turn += 1;
+        }
+        self.order.insert(index, combatant);
+    }
+
+    fn describe_order(&self) -> String {
+        let order : Vec<String> = self.order.iter().map(|c| format!("{} {}", c.name, c.roll)).collect();
+        return format!("The order is {}. It's {}'s turn.", order.join(", "), self.order[self.turn].name);
+    }
+}
+impl Command for InitiativeCommand {
+    fn name(&self) -> String {
+        return String::from("Initiative");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command keeps track of turn order in a tabletop game.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"roll initiative\" to start a fight, then say each name and their initiative, like \"goblin fifteen\". Say a name and a bonus, like \"goblin plus two\", or just a name, and I'll roll for them. Say \"done\" once everyone's in. After that, say \"next turn\" to move on, \"whose turn is it\" to check, \"read the initiative order\" to hear the order, \"add\" or \"remove\" someone \"to initiative\" or \"from initiative\", and \"end initiative\" when the fight is over.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("initiative tracker"), String::from("turn order")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("roll initiative"), String::from("next turn"), String::from("whose turn is it"),
+            String::from("end initiative")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        return text.contains("initiative") || text.contains("next turn") || text.contains("whose turn") || text.contains("who's turn");
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let fighting = !self.order.is_empty();
+        let answer = if text.contains("add ") {
+            let who = text.split_once("add ").map(|(_, w)| w).unwrap_or("");
+            let who = who.split(" to ").next().unwrap_or(who);
+            match InitiativeCommand::parse_entry(who) {
+                Some((combatant, said)) => {
+                    self.insert(combatant);
+                    self.save();
+                    said
+                },
+                None => {String::from("I didn't catch who to add.")},
+            }
+        } else if text.contains("remove ") {
+            let who = text.split_once("remove ").map(|(_, w)| w).unwrap_or("");
+            // cleaned up the same way as when they were added, so "remove the goblin" finds "goblin"
+            let who = InitiativeCommand::clean(who.split(" from ").next().unwrap_or(who));
+            let who = who.as_str();
+            match self.order.iter().position(|c| c.name == who) {
+                Some(index) => {
+                    self.order.remove(index);
+                    if index < self.turn {
+                        self.turn -= 1;
+                    } else if self.turn >= self.order.len() {
+                        self.turn = 0;
+                    }
+                    self.save();
+                    format!("Okay, {} is out of the fight.", who)
+                },
+                None => {format!("There's no one called {} in the fight.", who)},
+            }
+        } else if text.split_whitespace().any(|w| END_WORDS.contains(&w)) {
+            self.order.clear();
+            self.turn = 0;
+            self.round = 1;
+            self.save();
+            String::from("Okay, the fight is over.")
+        } else if text.contains("next") && fighting {
+            self.turn += 1;
+            let mut answer = String::new();
+            if self.turn >= self.order.len() {
+                self.turn = 0;
+                self.round += 1;
+                answer = format!("Round {}. ", self.round);
+            }
+            self.save();
+            answer + &format!("It's {}'s turn.", self.order[self.turn].name)
+        } else if text.contains("turn") && fighting {
+            format!("It's {}'s turn, in round {}.", self.order[self.turn].name, self.round)
+        } else if (text.contains("order") || text.contains("read")) && fighting {
+            self.describe_order()
+        } else if text.contains("next") || text.contains("turn") || text.contains("order") || text.contains("read") {
+            String::from("There's no fight going on. Say \"roll initiative\" to start one.")
+        } else {
+            speak.send(SpeakMessage::Say(String::from("Who's in the fight? Say each name and their initiative, or just a name and I'll roll for them. Say \"done\" once everyone's in."))).unwrap();
+            return CommandResult::Continue(Box::new(Vec::<Combatant>::new()));
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+    fn resume(&mut self, state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let mut entries = match state.downcast::<Vec<Combatant>>() {
+            Ok(e) => {*e},
+            Err(_) => {return CommandResult::Done},
+        };
+        if DONE_PHRASES.contains(&text.trim()) {
+            if entries.is_empty() {
+                speak.send(SpeakMessage::Say(String::from("No one's in the fight, so never mind."))).unwrap();
+                return CommandResult::Done;
+            }
+            // a stable sort, so ties stay in the order they were given
+            entries.sort_by(|a, b| b.roll.cmp(&a.roll));
+            self.order = entries;
+            self.turn = 0;
+            self.round = 1;
+            self.save();
+            speak.send(SpeakMessage::Say(self.describe_order())).unwrap();
+            return CommandResult::Done;
+        }
+        match InitiativeCommand::parse_entry(&text) {
+            Some((combatant, said)) => {
+                entries.push(combatant);
+                speak.send(SpeakMessage::Say(said)).unwrap();
+            },
+            None => {
+                speak.send(SpeakMessage::Say(String::from("I didn't catch that. Say a name and their initiative, or \"done\"."))).unwrap();
+            }
+        }
+        return CommandResult::Continue(Box::new(entries));
+    }
+}
@@ -1,23 +1,205 @@
-use crate::commands::{Command, CommandResult};
+use crate::commands::{Command, CommandResult, SessionState};
 use std::sync::mpsc::{Sender};
 use crate::SpeakMessage;
-use reqwest;
+use crate::commands::custom::get_string;
+use crate::http::HttpService;
+use crate::storage::Storage;
+use std::fs;
+use std::time::Duration;
+use json;
+use rand;
+use toml;
 
-pub struct JokeCommand {}
+// the jokes that come with ZINNIA
+const BUNDLED_JOKES : &str = "resources/jokes.toml";
+// more jokes added by the user, in the same format
+const USER_JOKES : &str = "jokes.toml";
+// where the jokes told recently are saved, so they aren't told again right away
+const HISTORY_FILE : &str = "jokes.json";
+// how many of the last jokes told are kept out of the running
+const HISTORY_SIZE : usize = 20;
+// the category that's told back and forth
+const KNOCK_KNOCK : &str = "knock knock";
+// words that come before "joke" without being a kind of joke, like "tell me another joke"
+const NOT_CATEGORIES : [&str; 16] = ["a", "an", "another", "me", "some", "funny", "good", "new", "the", "your",
+    "best", "random", "one", "more", "any", "great"];
+
+struct Joke {
+    category : String,
+    setup : Option<String>, // for a knock knock joke, who's at the door
+    punchline : String,
+}
+impl Joke {
+    fn parse(table : &toml::Table) -> Result<Joke, String> {
+        let category = get_string(table, "category")?.unwrap_or(String::from("general")).to_lowercase();
+        if let Some(text) = get_string(table, "text")? {
+            return Ok(Joke{category, setup : None, punchline : text});
+        }
+        let setup = get_string(table, "setup")?.ok_or("a joke needs either \"text\" or a \"setup\" and \"punchline\"")?;
+        let punchline = get_string(table, "punchline")?.ok_or("a joke with a \"setup\" needs a \"punchline\"")?;
+        return Ok(Joke{category, setup : Some(setup), punchline});
+    }
+
+    // what the joke is remembered by in the history
+    fn id(&self) -> String {
+        match &self.setup {
+            Some(setup) => {return format!("{} {}", setup, self.punchline)},
+            None => {return self.punchline.clone()},
+        }
+    }
+}
+
+// how far into a knock knock joke the conversation is
+enum KnockKnock {
+    WhosThere(usize), // waiting for "who's there?"
+    Who(usize), // waiting for "___ who?"
+}
+
+pub struct JokeCommand {
+    jokes : Vec<Joke>,
+    recent : Vec<String>, // the ids of the last jokes told, oldest first
+    http : HttpService,
+    storage : Storage,
+}
 impl JokeCommand {
-    // for sending HTTP requests
-    fn request(url : String) -> Result<String, &'static str> {
-        let client = reqwest::blocking::Client::new();
-        let response = client.get(url)
-            .header(reqwest::header::ACCEPT, "text/plain")
-            .send().or(Err("I had a problem connecting to the joke service"))?;
-        if response.status().is_success() {
-            let text = response.text().or(Err("I had a problem understanding the joke service."))?;
-            return Ok(text);
-        } else {
-            return Err("I didn't get a response from the joke service.");
+    pub fn new(http : HttpService, storage : Storage) -> JokeCommand {
+        let mut jokes = JokeCommand::load(BUNDLED_JOKES);
+        if fs::metadata(USER_JOKES).is_ok() {
+            jokes.extend(JokeCommand::load(USER_JOKES));
+        }
+        let recent = match storage.load(HISTORY_FILE) {
+            Some(saved) => {saved["recent"].members().filter_map(|j| j.as_str()).map(String::from).collect()},
+            None => {Vec::new()},
+        };
+        return JokeCommand{jokes, recent, http, storage};
+    }
+
+    // reads the jokes from a TOML file, leaving out any that have problems
+    fn load(path : &str) -> Vec<Joke> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => {c},
+            Err(e) => {
+                eprintln!("Error reading jokes from {}: {}", path, e);
+                return Vec::new();
+            }
+        };
+        let table = match contents.parse::<toml::Table>() {
+            Ok(t) => {t},
+            Err(e) => {
+                eprintln!("Error reading jokes from {}: {}", path, e);
+                return Vec::new();
+            }
+        };
+        let mut jokes = Vec::new();
+        let list = table.get("joke").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        for (i, item) in list.iter().enumerate() {
+            let parsed = item.as_table().ok_or(String::from("it is not a table")).and_then(Joke::parse);
+            match parsed {
+                Ok(j) => {jokes.push(j)},
+                Err(e) => {eprintln!("Error with joke {} in {}, skipping it: {}", i + 1, path, e)},
+            }
+        }
+        return jokes;
+    }
+
+    // every category there's a joke for, in the order they first show up
+    fn categories(&self) -> Vec<String> {
+        let mut categories : Vec<String> = Vec::new();
+        for joke in &self.jokes {
+            if !categories.contains(&joke.category) {
+                categories.push(joke.category.clone());
+            }
+        }
+        return categories;
+    }
+
+    // the category asked for, if any. If a kind of joke was asked for that there aren't any of, it's given back as the error
+    fn parse_category(&self, text : &str) -> Result<Option<String>, String> {
+        for category in self.categories() {
+            if text.contains(category.as_str()) {
+                return Ok(Some(category));
+            }
+        }
+        let words : Vec<&str> = text.split_whitespace().collect();
+        if let Some(i) = words.iter().position(|w| *w == "joke" || *w == "jokes") {
+            if i > 0 && !NOT_CATEGORIES.contains(&words[i - 1]) {
+                return Err(String::from(words[i - 1]));
+            }
+        }
+        return Ok(None);
+    }
+
+    // picks a joke from the category, avoiding the ones told recently if there are any others
+    fn pick(&self, category : Option<&str>) -> Option<usize> {
+        let matching : Vec<usize> = (0..self.jokes.len())
+            .filter(|i| category.is_none_or(|c| self.jokes[*i].category == c))
+            .collect();
+        let mut choices : Vec<usize> = matching.iter().copied().filter(|i| !self.recent.contains(&self.jokes[*i].id())).collect();
+        if choices.is_empty() {
+            // they've all been told lately, so just don't tell the very last one again
+            choices = matching.iter().copied().filter(|i| self.recent.last() != Some(&self.jokes[*i].id())).collect();
+        }
+        if choices.is_empty() {
+            choices = matching;
+        }
+        if choices.is_empty() {
+            return None;
+        }
+        return Some(choices[rand::random::<usize>() % choices.len()]);
+    }
+
+    // adds a joke to the history and saves it
+    fn remember(&mut self, id : String) {
+        self.recent.retain(|r| *r != id);
+        self.recent.push(id);
+        if self.recent.len() > HISTORY_SIZE {
+            self.recent.remove(0);
+        }
+        let saved = json::object!{
+            recent : self.recent.clone(),
+        };
+        if let Err(e) = self.storage.save(HISTORY_FILE, &saved) {
+            eprintln!("Error saving joke history: {}", e);
+        }
+    }
+
+    // gets a dad joke from icanhazdadjoke.com, if it's reachable and the joke is a new one
+    fn online_joke(&self) -> Option<String> {
+        let url = self.http.base_url("joke", "https://icanhazdadjoke.com");
+        // a joke isn't worth waiting around for, so this gets a shorter timeout than usual
+        match self.http.get_with_timeout(&url, "text/plain", Duration::from_secs(5)) {
+            Ok(joke) => {
+                let joke = String::from(joke.trim());
+                if joke.is_empty() || self.recent.contains(&joke) {
+                    return None;
+                }
+                return Some(joke);
+            },
+            Err(e) => {
+                eprintln!("Not using the joke service: {}", e.describe("joke service"));
+                return None;
+            }
         }
     }
+
+    // starts telling a joke, which for a knock knock joke is only the first line
+    fn tell(&mut self, index : usize, speak : &Sender<SpeakMessage>) -> CommandResult {
+        self.remember(self.jokes[index].id());
+        let joke = &self.jokes[index];
+        match &joke.setup {
+            Some(_) if joke.category == KNOCK_KNOCK => {
+                speak.send(SpeakMessage::Say(String::from("Knock knock."))).unwrap();
+                return CommandResult::Continue(Box::new(KnockKnock::WhosThere(index)));
+            },
+            Some(setup) => {
+                speak.send(SpeakMessage::Say(format!("{} {}", setup, joke.punchline))).unwrap();
+            },
+            None => {
+                speak.send(SpeakMessage::Say(joke.punchline.clone())).unwrap();
+            }
+        }
+        return CommandResult::Done;
+    }
 }
 impl Command for JokeCommand {
     fn name(&self) -> String {
@@ -27,26 +209,87 @@ impl Command for JokeCommand {
         return String::from("This command will tell you a joke.");
     }
     fn help(&self) -> String {
-        return String::from("Ask for a joke and you will recieve one.");
+        return String::from("Ask for a joke and you will recieve one. You can ask for a kind of joke, like a knock knock joke or an animal joke, or ask what kinds of jokes there are. For a knock knock joke, answer each line like you normally would. Dad jokes come from the internet when it's available. More jokes can be added in jokes.toml.");
     }
     fn uses_internet(&self) -> bool {
+        return false;
+    }
+    // a dad joke might have to wait on the internet
+    fn runs_long(&self) -> bool {
         return true;
     }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("jokes"), String::from("funny"), String::from("knock knock")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("tell me a joke"), String::from("tell me a knock knock joke"),
+            String::from("what kinds of jokes do you know")];
+    }
     fn recognize(&self, text : String) -> bool {
         return text.contains("joke");
     }
-    fn effect(&mut self, _text : String, speak : Sender<SpeakMessage>) -> CommandResult {
-        let response = JokeCommand::request(String::from("https://icanhazdadjoke.com"));
-        let stringy : String = match response {
-            Ok(s) => {s},
-            Err(e) => {
-                speak.send(SpeakMessage::Say(format!("{} Please try again later.", e))).unwrap();
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        if text.contains("kind") || text.contains("type") || text.contains("categor") {
+            let categories : Vec<String> = self.categories().iter().map(|c| format!("{} jokes", c)).collect();
+            let answer = match categories.len() {
+                0 => {String::from("I don't know any jokes right now.")},
+                1 => {format!("I know {}.", categories[0])},
+                n => {format!("I know {}, and {}.", categories[..n - 1].join(", "), categories[n - 1])},
+            };
+            speak.send(SpeakMessage::Say(answer)).unwrap();
+            return CommandResult::Done;
+        }
+        let category = match self.parse_category(&text) {
+            Ok(c) => {c},
+            Err(kind) => {
+                speak.send(SpeakMessage::Say(format!("I don't know any {} jokes, but here's another one.", kind))).unwrap();
+                None
+            }
+        };
+        // the bundled dad jokes are only a fallback for when the joke service can't be used
+        if category.as_deref() == Some("dad") {
+            if let Some(joke) = self.online_joke() {
+                println!("{}", joke);
+                self.remember(joke.clone());
+                speak.send(SpeakMessage::Say(joke)).unwrap();
                 return CommandResult::Done;
             }
+        }
+        match self.pick(category.as_deref()) {
+            Some(index) => {return self.tell(index, &speak)},
+            None => {
+                speak.send(SpeakMessage::Say(String::from("I don't know any jokes right now."))).unwrap();
+                return CommandResult::Done;
+            }
+        }
+    }
+    fn resume(&mut self, state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let state = match state.downcast::<KnockKnock>() {
+            Ok(s) => {*s},
+            Err(_) => {return CommandResult::Done},
         };
-        println!("{}", stringy);
-        speak.send(SpeakMessage::Say(stringy)).unwrap();
-        return CommandResult::Done;
+        // anything with "who" in it counts, since the exact words don't always come through
+        let answered = text.contains("who");
+        match state {
+            KnockKnock::WhosThere(index) => {
+                if !answered {
+                    speak.send(SpeakMessage::Say(String::from("You're supposed to say \"who's there?\""))).unwrap();
+                    return CommandResult::Continue(Box::new(state));
+                }
+                let setup = self.jokes[index].setup.clone().unwrap_or_default();
+                speak.send(SpeakMessage::Say(format!("{}.", setup))).unwrap();
+                return CommandResult::Continue(Box::new(KnockKnock::Who(index)));
+            },
+            KnockKnock::Who(index) => {
+                let joke = &self.jokes[index];
+                if !answered {
+                    let setup = joke.setup.clone().unwrap_or_default();
+                    speak.send(SpeakMessage::Say(format!("You're supposed to say \"{} who?\"", setup))).unwrap();
+                    return CommandResult::Continue(Box::new(state));
+                }
+                speak.send(SpeakMessage::Say(joke.punchline.clone())).unwrap();
+                return CommandResult::Done;
+            }
+        }
     }
 }
-
@@ -0,0 +1,415 @@
+use crate::commands::{Command, CommandResult, SessionState};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use crate::storage::Storage;
+use crate::commands::spoken::{YES_WORDS, capitalize, is_yes, spoken_list};
+use json;
+use json::JsonValue;
+
+// where the lists are kept
+const LISTS_FILE : &str = "lists.json";
+// where exported lists are written, inside the data folder
+const EXPORT_DIR : &str = "exports";
+// other names for the usual lists
+const LIST_ALIASES : [(&str, &str); 4] = [("grocery", "shopping"), ("groceries", "shopping"), ("todo", "to-do"), ("to do", "to-do")];
+// words that come before a list's name, like "the" in "the shopping list"
+const NAME_STOPS : [&str; 14] = ["the", "my", "a", "an", "to", "on", "from", "off", "of", "in", "new", "whole", "entire", "your"];
+// answers that mean nothing more is being added
+const DONE_PHRASES : [&str; 13] = ["no", "nope", "no thanks", "no thank you", "that's it", "that's all", "that is all", "done",
+    "i'm done", "nothing", "nothing else", "all done", "that's everything"];
+// what's said before an item to take it off a list
+const REMOVE_WORDS : [&str; 6] = ["remove ", "delete ", "take off ", "cross off ", "check off ", "take "];
+
+// where a conversation about a list is up to
+enum Conversation {
+    Adding(String), // asked "anything else?" about the named list
+    ChoosingList(Vec<String>), // asked which list these should go on
+    Clearing(String), // asked whether to really clear the named list
+}
+
+// shopping lists, to-do lists, and any other named lists, kept between runs
+pub struct ListCommand {
+    lists : Vec<(String, Vec<String>)>, // each list's name and what's on it
+    current : Option<String>, // the list last talked about, for things like "remove eggs"
+    storage : Storage,
+}
+impl ListCommand {
+    pub fn new(storage : Storage) -> ListCommand {
+        let mut lists = Vec::new();
+        if let Some(saved) = storage.load(LISTS_FILE) {
+            for list in saved["lists"].members() {
+                if let Some(name) = list["name"].as_str() {
+                    let items = list["items"].members().filter_map(|i| i.as_str()).map(String::from).collect();
+                    lists.push((String::from(name), items));
+                }
+            }
+        }
+        return ListCommand{lists, current : None, storage};
+    }
+
+    fn save(&self) {
+        let lists : Vec<JsonValue> = self.lists.iter().map(|(name, items)| json::object!{
+            name : name.clone(),
+            items : items.clone(),
+        }).collect();
+        if let Err(e) = self.storage.save(LISTS_FILE, &json::object!{lists : lists}) {
+            eprintln!("Error saving lists: {}", e);
+        }
+    }
+
+    // "to do list" and "todo list" both come out as "to-do list"
+    fn normalize(text : &str) -> String {
+        let mut text = format!(" {} ", text.trim());
+        for (alias, name) in LIST_ALIASES {
+            text = text.replace(&format!(" {} list", alias), &format!(" {} list", name));
+        }
+        return String::from(text.trim());
+    }
+
+    // the name of the list in some text, like "shopping" in "add milk to the shopping list"
+    fn parse_name(text : &str) -> Option<String> {
+        for marker in ["list called ", "list named "] {
+            if let Some((_, after)) = text.split_once(marker) {
+                return Some(String::from(after.trim()));
+            }
+        }
+        let words : Vec<&str> = text.split_whitespace().collect();
+        let end = words.iter().position(|w| *w == "list")?;
+        let start = words[..end].iter().rposition(|w| NAME_STOPS.contains(w) || w.ends_with("'s")).map(|i| i + 1).unwrap_or(0);
+        if start == end {
+            return None;
+        }
+        return Some(words[start..end].join(" "));
+    }
+
+    fn find(&self, name : &str) -> Option<usize> {
+        return self.lists.iter().position(|(n, _)| n == name);
+    }
+
+    // the list being talked about: the one named, or the last one used, or the only one there is
+    fn pick(&self, text : &str) -> Option<String> {
+        if let Some(name) = ListCommand::parse_name(text) {
+            return Some(name);
+        }
+        if self.current.is_some() {
+            return self.current.clone();
+        }
+        if self.lists.len() == 1 {
+            return Some(self.lists[0].0.clone());
+        }
+        return None;
+    }
+
+    // the things in "milk, eggs, and bread"
+    fn parse_items(text : &str) -> Vec<String> {
+        let spaced = text.replace(',', " , ");
+        let mut items = Vec::new();
+        let mut current : Vec<&str> = Vec::new();
+        for word in spaced.split_whitespace().chain([","]) {
+            if word == "and" || word == "," {
+                if !current.is_empty() {
+                    let item = current.join(" ");
+                    items.push(String::from(item.strip_prefix("some ").unwrap_or(&item)));
+                }
+                current.clear();
+            } else {
+                current.push(word);
+            }
+        }
+        return items;
+    }
+
+    // the item in something like "remove eggs from the shopping list"
+    fn removed_item(text : &str) -> Option<String> {
+        let rest = REMOVE_WORDS.iter().find_map(|w| text.split_once(w).map(|(_, after)| after))?;
+        let item = rest.split(" from ").next().unwrap_or(rest).trim();
+        let item = item.strip_suffix(" off").unwrap_or(item).trim();
+        let item = item.strip_prefix("the ").unwrap_or(item);
+        if item.is_empty() {
+            return None;
+        }
+        return Some(String::from(item));
+    }
+
+    // which list has the item, and where, preferring the named list
+    fn find_item(&self, name : Option<&String>, item : &str) -> Option<(usize, usize)> {
+        let lists : Vec<usize> = match name.and_then(|n| self.find(n)) {
+            Some(index) => {vec![index]},
+            None => {(0..self.lists.len()).collect()},
+        };
+        // an exact match first, then anything close, like "eggs" for "a dozen eggs"
+        for exact in [true, false] {
+            for list in &lists {
+                let found = self.lists[*list].1.iter().position(|i| {
+                    let i = i.to_lowercase();
+                    if exact {i == item} else {i.contains(item) || item.contains(&i)}
+                });
+                if let Some(index) = found {
+                    return Some((*list, index));
+                }
+            }
+        }
+        return None;
+    }
+
+    fn label(name : &str) -> String {
+        return format!("{} list", name);
+    }
+
+    // puts things on a list, making the list if it's new, and asks if there's more
+    fn add(&mut self, name : String, items : Vec<String>, speak : &Sender<SpeakMessage>) -> CommandResult {
+        let index = match self.find(&name) {
+            Some(i) => {i},
+            None => {
+                self.lists.push((name.clone(), Vec::new()));
+                self.lists.len() - 1
+            },
+        };
+        let mut added = Vec::new();
+        let mut already = Vec::new();
+        for item in items {
+            if self.lists[index].1.iter().any(|i| i.to_lowercase() == item.to_lowercase()) {
+                already.push(item);
+            } else {
+                self.lists[index].1.push(item.clone());
+                added.push(item);
+            }
+        }
+        self.save();
+        self.current = Some(name.clone());
+        let mut answer = String::new();
+        if !added.is_empty() {
+            answer += &format!("Added {} to your {}. ", spoken_list(&added), ListCommand::label(&name));
+        }
+        if !already.is_empty() {
+            answer += &format!("{} {} already on it. ", capitalize(&spoken_list(&already)), if already.len() == 1 {"is"} else {"are"});
+        }
+        speak.send(SpeakMessage::Say(answer + "Anything else?")).unwrap();
+        return CommandResult::Continue(Box::new(Conversation::Adding(name)));
+    }
+
+    fn read(&self, name : &str) -> String {
+        return match self.find(name) {
+            Some(index) if !self.lists[index].1.is_empty() => {
+                format!("Your {} has {}.", ListCommand::label(name), spoken_list(&self.lists[index].1))
+            },
+            Some(_) => {format!("Your {} is empty.", ListCommand::label(name))},
+            None => {format!("You don't have a {}.", ListCommand::label(name))},
+        };
+    }
+
+    // writes a list out as Markdown, or plain text if asked for, into the exports folder
+    fn export(&self, name : &str, text : &str) -> String {
+        let index = match self.find(name) {
+            Some(i) => {i},
+            None => {return format!("You don't have a {}.", ListCommand::label(name))},
+        };
+        let title = capitalize(&ListCommand::label(name));
+        let items = &self.lists[index].1;
+        let plain = text.contains("text") || text.contains("plain");
+        let (contents, extension, kind) = if plain {
+            (format!("{}\n\n{}\n", title, items.join("\n")), "txt", "a text file")
+        } else {
+            let lines : Vec<String> = items.iter().map(|i| format!("- [ ] {}", i)).collect();
+            (format!("# {}\n\n{}\n", title, lines.join("\n")), "md", "a Markdown file")
+        };
+        let file = format!("{}/{}.{}", EXPORT_DIR, ListCommand::label(name).replace(' ', "_"), extension);
+        match self.storage.save_text(&file, &contents) {
+            Ok(()) => {return format!("I saved your {} as {}, at {}.", ListCommand::label(name), kind, self.storage.path(&file).display())},
+            Err(e) => {
+                eprintln!("Error exporting {}: {}", file, e);
+                return format!("I couldn't save your {}.", ListCommand::label(name));
+            },
+        }
+    }
+}
+impl Command for ListCommand {
+    fn name(&self) -> String {
+        return String::from("Lists");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command keeps shopping lists, to-do lists, and any other lists you want.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"add\" something \"to the shopping list\", or any other list, and I'll ask if there's anything else. Say \"what's on my to-do list\" to hear a list, \"remove\" something to take it off, and \"clear the list\" to empty it. Say \"delete my\" name \"list\" to get rid of a list, and \"what lists do I have\" to hear them all. Say \"export my shopping list\" to save it as a Markdown file, or \"as text\" for a plain text file.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("list"), String::from("shopping list"), String::from("to-do list")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("add milk to the shopping list"), String::from("what's on my to-do list"),
+            String::from("remove eggs"), String::from("clear the list")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        if text.split_whitespace().any(|w| w == "list" || w == "lists") {
+            return true;
+        }
+        // "remove eggs" doesn't say which list, so only take it if something's called that
+        return ListCommand::removed_item(&text).is_some_and(|item| self.find_item(None, &item).is_some());
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let text = ListCommand::normalize(&text);
+        let words : Vec<&str> = text.split_whitespace().collect();
+        let answer = if words.contains(&"lists") && !text.contains("export") {
+            let names : Vec<String> = self.lists.iter().map(|(n, _)| n.clone()).collect();
+            match names.len() {
+                0 => {String::from("You don't have any lists yet.")},
+                1 => {format!("You have a {}.", ListCommand::label(&names[0]))},
+                _ => {format!("You have lists for {}.", spoken_list(&names))},
+            }
+        } else if text.contains("export") || text.contains("markdown") || text.contains("text file") {
+            match self.pick(&text) {
+                Some(name) => {self.export(&name, &text)},
+                None => {String::from("Which list should I export?")},
+            }
+        } else if let Some(rest) = text.strip_prefix("add ").or_else(|| text.strip_prefix("put ")).or_else(|| text.split_once(" add ").map(|(_, r)| r)) {
+            // the items are everything before "to the shopping list"
+            let items_text = [" to ", " on ", " onto "].iter().filter_map(|m| rest.rsplit_once(m))
+                .filter(|(_, after)| after.split_whitespace().any(|w| w == "list")).map(|(before, _)| before).next().unwrap_or(rest);
+            let items = ListCommand::parse_items(items_text);
+            if items.is_empty() {
+                String::from("I didn't catch what to add.")
+            } else {
+                match self.pick(&text) {
+                    Some(name) => {return self.add(name, items, &speak)},
+                    None => {
+                        speak.send(SpeakMessage::Say(String::from("Which list should that go on?"))).unwrap();
+                        return CommandResult::Continue(Box::new(Conversation::ChoosingList(items)));
+                    },
+                }
+            }
+        } else if text.contains("clear") || text.contains("empty") {
+            match self.pick(&text).filter(|n| self.find(n).is_some()) {
+                Some(name) => {
+                    let count = self.lists[self.find(&name).unwrap()].1.len();
+                    if count == 0 {
+                        format!("Your {} is already empty.", ListCommand::label(&name))
+                    } else {
+                        self.current = Some(name.clone());
+                        speak.send(SpeakMessage::Say(format!("Clear all {} things off your {}?", count, ListCommand::label(&name)))).unwrap();
+                        return CommandResult::Continue(Box::new(Conversation::Clearing(name)));
+                    }
+                },
+                None => {String::from("Which list should I clear?")},
+            }
+        } else if let Some(item) = ListCommand::removed_item(&text) {
+            let name = ListCommand::parse_name(&text);
+            if item.split_whitespace().last() == Some("list") {
+                // the whole list, like "delete my packing list"
+                match name.as_ref().and_then(|n| self.find(n)) {
+                    Some(index) => {
+                        let (name, _) = self.lists.remove(index);
+                        if self.current.as_ref() == Some(&name) {
+                            self.current = None;
+                        }
+                        self.save();
+                        format!("Okay, I've deleted your {}.", ListCommand::label(&name))
+                    },
+                    None => {String::from("I couldn't find that list.")},
+                }
+            } else {
+                let name = name.or(self.current.clone());
+                match self.find_item(name.as_ref(), &item) {
+                    Some((list, index)) => {
+                        let removed = self.lists[list].1.remove(index);
+                        let list_name = self.lists[list].0.clone();
+                        self.current = Some(list_name.clone());
+                        self.save();
+                        format!("Took {} off your {}.", removed, ListCommand::label(&list_name))
+                    },
+                    None => {format!("I couldn't find {} on your lists.", item)},
+                }
+            }
+        } else if text.contains("create") || text.contains("make") || text.contains("start") || text.contains("new ") {
+            match ListCommand::parse_name(&text) {
+                Some(name) => {
+                    if self.find(&name).is_none() {
+                        self.lists.push((name.clone(), Vec::new()));
+                        self.save();
+                    }
+                    self.current = Some(name.clone());
+                    speak.send(SpeakMessage::Say(format!("Okay, I've made a {}. What should go on it?", ListCommand::label(&name)))).unwrap();
+                    return CommandResult::Continue(Box::new(Conversation::Adding(name)));
+                },
+                None => {String::from("What should the list be called?")},
+            }
+        } else {
+            match self.pick(&text) {
+                Some(name) => {
+                    self.current = Some(name.clone());
+                    self.read(&name)
+                },
+                None => {String::from("Which list? Say something like \"what's on my shopping list\".")},
+            }
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+    fn resume(&mut self, state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let conversation = match state.downcast::<Conversation>() {
+            Ok(c) => {*c},
+            Err(_) => {return CommandResult::Done},
+        };
+        let text = text.trim().trim_end_matches(" please");
+        match conversation {
+            Conversation::Adding(name) => {
+                // "no that's all" or "nope nothing else thanks" mean done too, but "noodles" doesn't
+                if DONE_PHRASES.iter().any(|p| text == *p || text.starts_with(&format!("{} ", p))) {
+                    let count = self.find(&name).map(|i| self.lists[i].1.len()).unwrap_or(0);
+                    let things = if count == 1 {"thing"} else {"things"};
+                    speak.send(SpeakMessage::Say(format!("Okay, your {} has {} {} on it.", ListCommand::label(&name), count, things))).unwrap();
+                    return CommandResult::Done;
+                }
+                if YES_WORDS.contains(&text) {
+                    speak.send(SpeakMessage::Say(String::from("What else should I add?"))).unwrap();
+                    return CommandResult::Continue(Box::new(Conversation::Adding(name)));
+                }
+                // something for the list rather than on it, like "read my list" or "remove milk", is done instead of added
+                let for_list = text.split_whitespace().any(|w| w == "list" || w == "lists")
+                    || REMOVE_WORDS.iter().any(|w| text.starts_with(w))
+                    || ["what's on", "what is on", "read "].iter().any(|p| text.starts_with(p));
+                if for_list {
+                    self.current = Some(name);
+                    return self.effect(String::from(text), speak);
+                }
+                let mut rest = text;
+                for filler in ["yes ", "yeah ", "and ", "also ", "add ", "some "] {
+                    rest = rest.strip_prefix(filler).unwrap_or(rest);
+                }
+                return self.add(name, ListCommand::parse_items(rest), &speak);
+            },
+            Conversation::ChoosingList(items) => {
+                let normalized = ListCommand::normalize(text);
+                let name = match ListCommand::parse_name(&normalized) {
+                    Some(n) => {n},
+                    None => {
+                        let name = normalized.trim_start_matches("the ").trim_start_matches("my ").trim_end_matches(" list");
+                        String::from(name.trim())
+                    },
+                };
+                if name.is_empty() {
+                    speak.send(SpeakMessage::Say(String::from("I didn't catch a list. Which list should that go on?"))).unwrap();
+                    return CommandResult::Continue(Box::new(Conversation::ChoosingList(items)));
+                }
+                return self.add(name, items, &speak);
+            },
+            Conversation::Clearing(name) => {
+                // clearing can't be undone, so "yesterday" or "yeah no" don't count
+                let answer = if is_yes(text) {
+                    if let Some(index) = self.find(&name) {
+                        self.lists[index].1.clear();
+                        self.save();
+                    }
+                    format!("Okay, your {} is empty.", ListCommand::label(&name))
+                } else {
+                    format!("Okay, I'll leave your {} alone.", ListCommand::label(&name))
+                };
+                speak.send(SpeakMessage::Say(answer)).unwrap();
+                return CommandResult::Done;
+            },
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::commands::{Command, CommandResult, SessionState};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use crate::storage::Storage;
+use crate::commands::clock::spoken_time;
+use crate::commands::spoken::capitalize;
+use chrono::{Local, NaiveDateTime};
+use std::fs;
+
+// where notes are kept, inside the data folder, one file each
+const NOTES_DIR : &str = "notes";
+// how a note's file is named, from when it was started
+const NOTE_NAME_FORMAT : &str = "%Y-%m-%d_%H-%M-%S";
+// how long a name in that format is, like "2024-05-17_15-04-32"
+const NOTE_NAME_LENGTH : usize = 19;
+// what ends a note, which can also come at the end of the last thing said
+const END_PHRASES : [&str; 7] = ["end note", "end the note", "end of note", "stop note", "finish note", "end dictation", "stop dictation"];
+// what throws a note away instead of keeping it
+const DISCARD_PHRASES : [&str; 5] = ["cancel note", "cancel the note", "discard note", "discard the note", "delete this note"];
+// what starts a note, and anything said after it is the start of the note
+const START_PHRASES : [&str; 6] = ["take a note", "make a note", "start a note", "new note", "dictation", "dictate"];
+
+// a note being dictated
+struct Dictation {
+    file : String,
+    lines : usize,
+}
+
+// takes notes by dictation, saving each one to its own file, and reads them back
+pub struct NoteCommand {
+    storage : Storage,
+}
+impl NoteCommand {
+    pub fn new(storage : Storage) -> NoteCommand {
+        return NoteCommand{storage};
+    }
+
+    // adds something said to the note, as its own sentence
+    fn write(&self, dictation : &mut Dictation, text : &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        if let Err(e) = self.storage.append_text(&dictation.file, &format!("{}.\n", capitalize(text))) {
+            eprintln!("Error writing to {}: {}", dictation.file, e);
+        }
+        dictation.lines += 1;
+    }
+
+    // a file name for a new note, from when it was started, with a number after it if there's already a note from then
+    // the number has two digits so the names still sort in the order they were made
+    fn new_file(&self) -> String {
+        let stamp = Local::now().format(NOTE_NAME_FORMAT).to_string();
+        let mut file = format!("{}/{}.txt", NOTES_DIR, stamp);
+        let mut count = 2;
+        while self.storage.path(&file).exists() {
+            file = format!("{}/{}_{:02}.txt", NOTES_DIR, stamp, count);
+            count += 1;
+        }
+        return file;
+    }
+
+    // the newest note's file name, which sorts last since it's named by when it was started
+    fn last_note(&self) -> Option<String> {
+        let entries = fs::read_dir(self.storage.path(NOTES_DIR)).ok()?;
+        let mut names : Vec<String> = entries.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().into_owned())
+            .filter(|n| n.ends_with(".txt")).collect();
+        names.sort();
+        return names.pop();
+    }
+
+    fn read_last(&self) -> String {
+        let name = match self.last_note() {
+            Some(n) => {n},
+            None => {return String::from("You don't have any notes yet. Say \"take a note\" to start one.")},
+        };
+        let contents = match fs::read_to_string(self.storage.path(&format!("{}/{}", NOTES_DIR, name))) {
+            Ok(c) => {c},
+            Err(e) => {
+                eprintln!("Error reading note {}: {}", name, e);
+                return String::from("I couldn't read your last note.");
+            },
+        };
+        // the name can have a number after the time, for a second note started in the same second
+        let stamp = name.trim_end_matches(".txt");
+        let stamp = stamp.get(..NOTE_NAME_LENGTH).unwrap_or(stamp);
+        let when = match NaiveDateTime::parse_from_str(stamp, NOTE_NAME_FORMAT) {
+            Ok(t) => {format!(", from {} at {},", t.format("%A, %B %-d"), spoken_time(t.time()))},
+            Err(_) => {String::new()},
+        };
+        let contents = contents.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if contents.is_empty() {
+            return String::from("Your last note is empty.");
+        }
+        return format!("Your last note{} says: {}", when, contents);
+    }
+}
+impl Command for NoteCommand {
+    fn name(&self) -> String {
+        return String::from("Notes");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command takes notes by dictation and reads them back.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"take a note\", then say what you want written down, as much as you like. Everything you say goes in the note until you say \"end note\", or \"cancel note\" to throw it away. If I don't hear anything for two minutes, or you pick \"Stop Listening\" from the tray, the note ends and what you said is kept. Say \"read my last note\" to hear it back. Notes are saved as text files in the notes folder.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("note"), String::from("dictation"), String::from("take a note")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("take a note"), String::from("end note"), String::from("read my last note")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        // "end dictation" with no note going is still for this command, it just doesn't start one
+        if START_PHRASES.iter().chain(&END_PHRASES).chain(&DISCARD_PHRASES).any(|p| find_phrase(&text, p).is_some()) {
+            return true;
+        }
+        return text.contains("note") && text.contains("read");
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        if END_PHRASES.iter().chain(&DISCARD_PHRASES).any(|p| find_phrase(&text, p).is_some()) {
+            speak.send(SpeakMessage::Say(String::from("There's no note being taken right now."))).unwrap();
+            return CommandResult::Done;
+        }
+        let start = START_PHRASES.iter().find_map(|p| find_phrase(&text, p).map(|i| &text[i + p.len()..]));
+        let rest = match start {
+            Some(rest) => {rest},
+            None => {
+                speak.send(SpeakMessage::Say(self.read_last())).unwrap();
+                return CommandResult::Done;
+            },
+        };
+        let mut dictation = Dictation{file : self.new_file(), lines : 0};
+        // anything said right after "take a note" is the start of it
+        let rest = rest.trim();
+        self.write(&mut dictation, rest.strip_prefix("that ").unwrap_or(rest));
+        speak.send(SpeakMessage::Say(String::from("Okay, go ahead. Say \"end note\" when you're done."))).unwrap();
+        return CommandResult::Continue(Box::new(dictation));
+    }
+    fn resume(&mut self, state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let mut dictation = match state.downcast::<Dictation>() {
+            Ok(d) => {*d},
+            Err(_) => {return CommandResult::Done},
+        };
+        let text = text.trim();
+        if DISCARD_PHRASES.iter().any(|p| ends_with_phrase(text, p)) {
+            if dictation.lines > 0 {
+                if let Err(e) = fs::remove_file(self.storage.path(&dictation.file)) {
+                    eprintln!("Error deleting {}: {}", dictation.file, e);
+                }
+            }
+            speak.send(SpeakMessage::Say(String::from("Okay, I threw that note away."))).unwrap();
+            return CommandResult::Done;
+        }
+        if let Some(end) = END_PHRASES.iter().find(|p| ends_with_phrase(text, p)) {
+            self.write(&mut dictation, text.trim_end_matches(end));
+            let answer = match dictation.lines {
+                0 => {String::from("There was nothing to write down, so I didn't save a note.")},
+                1 => {String::from("Okay, I saved your note.")},
+                n => {format!("Okay, I saved your note, {} lines long.", n)},
+            };
+            speak.send(SpeakMessage::Say(answer)).unwrap();
+            return CommandResult::Done;
+        }
+        // nothing is said back while taking a note, so it doesn't get written down too
+        self.write(&mut dictation, text);
+        return CommandResult::Continue(Box::new(dictation));
+    }
+    // what's been said so far is already written down, so ending early (after a long quiet, or from the tray) keeps it
+    fn end(&mut self, state : SessionState, speak : Sender<SpeakMessage>) {
+        let answer = match state.downcast::<Dictation>() {
+            Ok(d) if d.lines > 0 => {String::from("I stopped taking your note. What you said before that is saved.")},
+            _ => {String::from("I stopped taking your note. Nothing was written down, so I didn't save it.")},
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+    }
+    fn takes_raw_text(&self) -> bool {
+        return true;
+    }
+}
+
+// where a phrase is in the text as whole words, so "new note" isn't found in "renew note"
+fn find_phrase(text : &str, phrase : &str) -> Option<usize> {
+    return text.match_indices(phrase).map(|(i, _)| i).find(|i| {
+        let before = text[..*i].chars().next_back();
+        let after = text[i + phrase.len()..].chars().next();
+        return before.is_none_or(|c| c == ' ') && after.is_none_or(|c| c == ' ');
+    });
+}
+
+// whether the text ends with a phrase as whole words, so "send note" doesn't end with "end note"
+fn ends_with_phrase(text : &str, phrase : &str) -> bool {
+    return text == phrase || text.ends_with(&format!(" {}", phrase));
+}
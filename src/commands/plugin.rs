@@ -0,0 +1,337 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{self, Sender, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use crate::SpeakMessage;
+use std::ffi::{CStr, CString, c_char};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command as Process, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use libloading::{Library, Symbol};
+use json;
+use json::JsonValue;
+
+// Commands can be added without recompiling ZINNIA by putting a shared library (a Rust cdylib
+// or anything else that can export C functions) in the plugins directory. See lib/zinnia_plugin.h
+// for the functions it needs to export. Each plugin is loaded into its own copy of ZINNIA, started
+// with HOST_ARG, which the main program talks to one line of JSON at a time. That way a plugin that
+// panics, segfaults, aborts, or hangs only takes down its own process, and gets turned off.
+
+// bumped whenever the functions plugins export change
+const ABI_VERSION : u32 = 1;
+// the argument that starts ZINNIA as a plugin host instead of the assistant
+pub const HOST_ARG : &str = "--plugin-host";
+// what the host starts each of its own lines with, so they can't be mixed up with anything the plugin prints
+const REPLY_PREFIX : &str = "zinnia-plugin-reply ";
+// how long a plugin gets to answer before it's taken to be stuck
+const LOAD_TIMEOUT : Duration = Duration::from_secs(10);
+// recognize is asked on every utterance while listening, so it gets much less time
+const RECOGNIZE_TIMEOUT : Duration = Duration::from_millis(500);
+const EFFECT_TIMEOUT : Duration = Duration::from_secs(60);
+
+type VersionFn = unsafe extern "C-unwind" fn() -> u32;
+type StrFn = unsafe extern "C-unwind" fn() -> *const c_char;
+type BoolFn = unsafe extern "C-unwind" fn() -> bool;
+type RecognizeFn = unsafe extern "C-unwind" fn(*const c_char) -> bool;
+type EffectFn = unsafe extern "C-unwind" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C-unwind" fn(*mut c_char);
+
+// a plugin library, loaded into the plugin host process
+struct PluginLibrary {
+    recognize_fn : RecognizeFn,
+    effect_fn : EffectFn,
+    free_fn : FreeFn,
+    info : JsonValue, // the name, description, help, and whether it uses the internet
+    _lib : Library, // has to stay loaded for as long as the functions above are used
+}
+impl PluginLibrary {
+    fn load(path : &Path) -> Result<PluginLibrary, String> {
+        // loading a library runs its initializers, so this is only as safe as the plugins people install
+        let lib = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+        unsafe {
+            let version : Symbol<VersionFn> = lib.get(b"zinnia_plugin_abi_version").map_err(|e| e.to_string())?;
+            let version = version();
+            if version != ABI_VERSION {
+                return Err(format!("plugin is for version {} of the plugin interface, but this is version {}", version, ABI_VERSION));
+            }
+            let name : Symbol<StrFn> = lib.get(b"zinnia_plugin_name").map_err(|e| e.to_string())?;
+            let desc : Symbol<StrFn> = lib.get(b"zinnia_plugin_desc").map_err(|e| e.to_string())?;
+            let help : Symbol<StrFn> = lib.get(b"zinnia_plugin_help").map_err(|e| e.to_string())?;
+            let uses_internet : Symbol<BoolFn> = lib.get(b"zinnia_plugin_uses_internet").map_err(|e| e.to_string())?;
+            let recognize_fn : RecognizeFn = *lib.get(b"zinnia_plugin_recognize").map_err(|e| e.to_string())?;
+            let effect_fn : EffectFn = *lib.get(b"zinnia_plugin_effect").map_err(|e| e.to_string())?;
+            let free_fn : FreeFn = *lib.get(b"zinnia_plugin_free").map_err(|e| e.to_string())?;
+            let info = json::object!{
+                name : copy_str(name()),
+                desc : copy_str(desc()),
+                help : copy_str(help()),
+                uses_internet : uses_internet(),
+            };
+            return Ok(PluginLibrary{recognize_fn, effect_fn, free_fn, info, _lib : lib});
+        }
+    }
+
+    fn recognize(&self, text : &str) -> bool {
+        let text = match CString::new(text) {
+            Ok(t) => {t},
+            Err(_) => {return false},
+        };
+        return unsafe { (self.recognize_fn)(text.as_ptr()) };
+    }
+
+    fn effect(&self, text : &str) -> Option<String> {
+        let text = CString::new(text).ok()?;
+        unsafe {
+            let raw = (self.effect_fn)(text.as_ptr());
+            if raw.is_null() {
+                return None;
+            }
+            let s = copy_str(raw);
+            (self.free_fn)(raw);
+            return Some(s);
+        }
+    }
+}
+
+// runs as the plugin host: loads one plugin, says what it is, then answers requests from stdin until it closes
+// a panic or crash in the plugin ends this process, which the main program notices
+pub fn run_host(path : &str) {
+    let mut out = io::stdout();
+    let library = match PluginLibrary::load(Path::new(path)) {
+        Ok(l) => {l},
+        Err(e) => {
+            let _ = writeln!(out, "{}{}", REPLY_PREFIX, json::stringify(json::object!{id : 0, error : e}));
+            return;
+        },
+    };
+    let mut info = library.info.clone();
+    info["id"] = 0.into();
+    let _ = writeln!(out, "{}{}", REPLY_PREFIX, json::stringify(info));
+    let _ = out.flush();
+    for line in io::stdin().lock().lines() {
+        let request = match line.ok().and_then(|l| json::parse(&l).ok()) {
+            Some(r) => {r},
+            None => {break},
+        };
+        let result : JsonValue = if let Some(text) = request["recognize"].as_str() {
+            library.recognize(text).into()
+        } else if let Some(text) = request["effect"].as_str() {
+            library.effect(text).into()
+        } else {
+            JsonValue::Null
+        };
+        // the id is sent back so an answer can't be taken for the one to a different request
+        let reply = json::object!{id : request["id"].clone(), result : result};
+        if writeln!(out, "{}{}", REPLY_PREFIX, json::stringify(reply)).and_then(|_| out.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+// a running plugin host, which is killed when this is dropped
+struct Host {
+    child : Child,
+    stdin : ChildStdin,
+    replies : Receiver<JsonValue>,
+    next_id : u64, // numbers each request, starting after the 0 the host's first line is given
+}
+impl Host {
+    // starts a host for the plugin, giving back the host and what the plugin said about itself
+    fn start(path : &Path) -> Result<(Host, JsonValue), String> {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        let mut child = Process::new(exe)
+            .arg(HOST_ARG)
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        let stdin = child.stdin.take().ok_or("couldn't talk to the plugin host")?;
+        let stdout = child.stdout.take().ok_or("couldn't hear from the plugin host")?;
+        let (repliestx, replies) = mpsc::channel::<JsonValue>();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = match line {
+                    Ok(l) => {l},
+                    Err(_) => {break},
+                };
+                // anything the plugin prints itself doesn't have the prefix, and is passed along rather than read as an answer
+                // that includes the start of a line it didn't finish before the host's answer came after it
+                let reply = match line.split_once(REPLY_PREFIX) {
+                    Some((printed, reply)) => {
+                        if !printed.is_empty() {
+                            println!("{}", printed);
+                        }
+                        reply
+                    },
+                    None => {
+                        println!("{}", line);
+                        continue;
+                    },
+                };
+                match json::parse(reply) {
+                    Ok(reply) if reply.is_object() => {
+                        if repliestx.send(reply).is_err() {
+                            break;
+                        }
+                    },
+                    _ => {eprintln!("Error: a plugin host sent something that isn't an answer: {}", reply)},
+                }
+            }
+        });
+        let mut host = Host{child, stdin, replies, next_id : 1};
+        let info = host.receive(0, LOAD_TIMEOUT).ok_or("the plugin crashed or didn't answer while loading")?;
+        if let Some(e) = info["error"].as_str() {
+            return Err(String::from(e));
+        }
+        return Ok((host, info));
+    }
+
+    // waits for the answer to the request with the given id, skipping any others
+    fn receive(&mut self, id : u64, timeout : Duration) -> Option<JsonValue> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.replies.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(reply) => {
+                    if reply["id"].as_u64() == Some(id) {
+                        return Some(reply);
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {return None},
+            }
+        }
+    }
+
+    // sends a request and waits for the answer, giving back None if the host died or took too long
+    fn ask(&mut self, mut request : JsonValue, timeout : Duration) -> Option<JsonValue> {
+        let id = self.next_id;
+        self.next_id += 1;
+        request["id"] = id.into();
+        writeln!(self.stdin, "{}", json::stringify(request)).ok()?;
+        self.stdin.flush().ok()?;
+        return self.receive(id, timeout).map(|r| r["result"].clone());
+    }
+}
+impl Drop for Host {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// a command that lives in a dynamically loaded library, run in a plugin host
+pub struct PluginCommand {
+    name : String,
+    desc : String,
+    help : String,
+    uses_internet : bool,
+    host : Mutex<Option<Host>>, // None once the plugin crashes or hangs, after which it's never called again
+    recognized : Mutex<Option<(String, bool)>>, // the last text recognize was asked about and the answer, so it's only asked once
+}
+impl PluginCommand {
+    // loads every shared library in a directory as a command, skipping (and reporting) any that are broken
+    pub fn load_dir(dir : &str) -> Vec<PluginCommand> {
+        let mut plugins = Vec::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => {e},
+            Err(_) => {return plugins}, // no directory just means no plugins
+        };
+        let mut paths : Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION) {
+                match PluginCommand::load(&path) {
+                    Ok(p) => {
+                        println!("Loaded plugin \"{}\" from {}", p.name, path.display());
+                        plugins.push(p);
+                    },
+                    Err(e) => {eprintln!("Error loading plugin {}: {}", path.display(), e);}
+                }
+            }
+        }
+        return plugins;
+    }
+
+    fn load(path : &Path) -> Result<PluginCommand, String> {
+        let (host, info) = Host::start(path)?;
+        return Ok(PluginCommand {
+            name : String::from(info["name"].as_str().unwrap_or("")),
+            desc : String::from(info["desc"].as_str().unwrap_or("")),
+            help : String::from(info["help"].as_str().unwrap_or("")),
+            uses_internet : info["uses_internet"].as_bool().unwrap_or(false),
+            host : Mutex::new(Some(host)),
+            recognized : Mutex::new(None),
+        });
+    }
+
+    // asks the plugin host something, turning the plugin off if it crashed or got stuck
+    fn ask(&self, request : JsonValue, timeout : Duration) -> Result<JsonValue, ()> {
+        let mut host = self.host.lock().unwrap_or_else(|e| e.into_inner());
+        let answer = match host.as_mut() {
+            Some(h) => {h.ask(request, timeout)},
+            None => {return Err(())},
+        };
+        match answer {
+            Some(a) => {return Ok(a)},
+            None => {
+                eprintln!("Plugin \"{}\" crashed or stopped answering and has been disabled", self.name);
+                // dropping the host kills it
+                *host = None;
+                return Err(());
+            },
+        }
+    }
+}
+impl Command for PluginCommand {
+    fn name(&self) -> String {
+        return self.name.clone();
+    }
+    fn desc(&self) -> String {
+        return self.desc.clone();
+    }
+    fn help(&self) -> String {
+        return self.help.clone();
+    }
+    fn uses_internet(&self) -> bool {
+        return self.uses_internet;
+    }
+    // plugins talk to another process, so they always run on a worker instead of holding up listening
+    fn runs_long(&self) -> bool {
+        return true;
+    }
+    fn recognize(&self, text : String) -> bool {
+        let mut recognized = self.recognized.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((last, answer)) = recognized.as_ref() {
+            if *last == text {
+                return *answer;
+            }
+        }
+        let answer = self.ask(json::object!{recognize : text.clone()}, RECOGNIZE_TIMEOUT).ok().and_then(|a| a.as_bool()).unwrap_or(false);
+        *recognized = Some((text, answer));
+        return answer;
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        match self.ask(json::object!{effect : text}, EFFECT_TIMEOUT) {
+            Ok(answer) => {
+                // null means the plugin had nothing to say
+                if let Some(s) = answer.as_str().filter(|s| !s.is_empty()) {
+                    speak.send(SpeakMessage::Say(String::from(s))).unwrap();
+                }
+            },
+            Err(()) => {
+                speak.send(SpeakMessage::Say(format!("The {} plugin had a problem and has been turned off.", self.name))).unwrap();
+            },
+        }
+        return CommandResult::Done;
+    }
+}
+
+// copies a string owned by the plugin into one owned by ZINNIA
+unsafe fn copy_str(ptr : *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    return CStr::from_ptr(ptr).to_string_lossy().into_owned();
+}
@@ -0,0 +1,179 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use crate::commands::spoken::spoken_list;
+use text2num::{Language, replace_numbers_in_text};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+// the range used when a number is asked for without one
+const DEFAULT_RANGE : (i64, i64) = (1, 100);
+// the most coins that can be flipped at once
+const MAX_COINS : usize = 20;
+// phrases that come before the things to choose from or shuffle
+const CHOICE_PHRASES : [&str; 8] = ["choose between", "pick between", "decide between", "choose from", "pick from",
+    "choose one of", "pick one of", "shuffle"];
+// words left off the front of a list, like "shuffle these names"
+const LIST_FILLER : [&str; 7] = ["these names", "these", "the names", "names", "the", "up", "of"];
+
+// what was asked for
+enum Request {
+    Coin,
+    Number,
+    Choose,
+    Shuffle,
+}
+
+// flips coins, picks numbers, and chooses between things
+pub struct RandomCommand {}
+impl RandomCommand {
+    fn parse_request(text : &str) -> Option<Request> {
+        if text.contains("shuffle") {
+            return Some(Request::Shuffle);
+        }
+        if text.contains("coin") || text.contains("heads or tails") {
+            return Some(Request::Coin);
+        }
+        if text.contains("random number") || text.contains("pick a number") || text.contains("choose a number")
+            || text.contains("number between") || text.contains("number from") {
+            return Some(Request::Number);
+        }
+        if CHOICE_PHRASES.iter().any(|p| text.contains(p)) {
+            return Some(Request::Choose);
+        }
+        return None;
+    }
+
+    // every whole number in some text, with "minus" making the next one negative
+    fn numbers_in(text : &str) -> Vec<i64> {
+        let converted = replace_numbers_in_text(text, &Language::english(), 0.0);
+        let mut numbers = Vec::new();
+        let mut negative = false;
+        for word in converted.split_whitespace() {
+            if word == "minus" || word == "negative" {
+                negative = true;
+                continue;
+            }
+            if let Ok(n) = word.trim_matches(|c : char| !c.is_ascii_digit() && c != '-').replace(',', "").parse::<i64>() {
+                numbers.push(if negative {-n} else {n});
+            }
+            negative = false;
+        }
+        return numbers;
+    }
+
+    // the range to pick a number from, like "between one and fifty", "up to ten", or nothing for the default
+    fn parse_range(text : &str) -> (i64, i64) {
+        // the two ends are read separately first, since "one hundred and two hundred" reads as one number otherwise
+        let pieces : Vec<i64> = text.split(" and ").flat_map(|p| p.split(" to ")).flat_map(RandomCommand::numbers_in).collect();
+        let numbers = if pieces.len() == 2 {pieces} else {RandomCommand::numbers_in(text)};
+        match numbers.as_slice() {
+            [] => {return DEFAULT_RANGE},
+            [high] => {return (1.min(*high), 1.max(*high))},
+            [a, b, ..] => {return (*a.min(b), *a.max(b))},
+        }
+    }
+
+    // the things to choose from or shuffle, split on "and", "or", and commas
+    // without any of those, each word is its own thing, like "shuffle alice bob carol"
+    fn parse_items(text : &str) -> Vec<String> {
+        let mut rest = text;
+        for phrase in CHOICE_PHRASES {
+            if let Some((_, after)) = text.split_once(phrase) {
+                rest = after;
+                break;
+            }
+        }
+        let mut rest = rest.trim().trim_start_matches(':').trim();
+        for filler in LIST_FILLER {
+            rest = rest.strip_prefix(&format!("{} ", filler)).unwrap_or(rest);
+        }
+        let spaced = rest.replace(',', " , ");
+        let mut items : Vec<String> = Vec::new();
+        let mut current : Vec<&str> = Vec::new();
+        for word in spaced.split_whitespace() {
+            if word == "and" || word == "or" || word == "," {
+                if !current.is_empty() {
+                    items.push(current.join(" "));
+                }
+                current.clear();
+            } else {
+                current.push(word);
+            }
+        }
+        if !current.is_empty() {
+            items.push(current.join(" "));
+        }
+        if items.len() == 1 {
+            items = items[0].split_whitespace().map(String::from).collect();
+        }
+        return items;
+    }
+
+    fn flip(text : &str) -> String {
+        let count = (RandomCommand::numbers_in(text).first().copied().unwrap_or(1).max(1) as usize).min(MAX_COINS);
+        let mut rng = rand::thread_rng();
+        let flips : Vec<bool> = (0..count).map(|_| rng.gen_bool(0.5)).collect();
+        if count == 1 {
+            return String::from(if flips[0] {"It's heads."} else {"It's tails."});
+        }
+        let named : Vec<String> = flips.iter().map(|h| String::from(if *h {"heads"} else {"tails"})).collect();
+        let heads = flips.iter().filter(|h| **h).count();
+        return format!("I got {}. That's {} heads and {} tails.", spoken_list(&named), heads, count - heads);
+    }
+}
+impl Command for RandomCommand {
+    fn name(&self) -> String {
+        return String::from("Random");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command flips coins, picks random numbers, and chooses between things for you.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"flip a coin\", or a number of coins. Say \"pick a number between\" two numbers for a random number, or leave them out for one from 1 to 100. Say \"choose between\" some things, separated by \"and\" or \"or\", to have one picked, or \"shuffle\" and some things to put them in a random order.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("coin"), String::from("coin flip"), String::from("random number"), String::from("shuffle"),
+            String::from("choose")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("flip a coin"), String::from("pick a number between one and fifty"),
+            String::from("choose between pizza and tacos"), String::from("shuffle alice, bob, and carol")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        return RandomCommand::parse_request(&text).is_some();
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let answer = match RandomCommand::parse_request(&text) {
+            Some(Request::Coin) => {RandomCommand::flip(&text)},
+            Some(Request::Number) => {
+                let (low, high) = RandomCommand::parse_range(&text);
+                let number = rand::thread_rng().gen_range(low..=high);
+                if number < 0 {format!("Minus {}.", -number)} else {format!("{}.", number)}
+            },
+            Some(Request::Choose) => {
+                let items = RandomCommand::parse_items(&text);
+                match items.len() {
+                    0 => {String::from("I didn't hear anything to choose from.")},
+                    1 => {format!("Well, there's only {}.", items[0])},
+                    _ => {format!("I choose {}.", items[rand::thread_rng().gen_range(0..items.len())])},
+                }
+            },
+            Some(Request::Shuffle) => {
+                let mut items = RandomCommand::parse_items(&text);
+                if items.len() < 2 {
+                    String::from("I need at least two things to shuffle.")
+                } else {
+                    items.shuffle(&mut rand::thread_rng());
+                    format!("Here's the order: {}.", spoken_list(&items))
+                }
+            },
+            None => {String::from("I'm not sure what you want me to pick.")},
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+}
@@ -0,0 +1,392 @@
+use crate::commands::{Command, CommandResult, SessionState};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use crate::storage::Storage;
+use crate::commands::alarm::unit_millis;
+use crate::commands::clock::spoken_time;
+use crate::commands::spoken::{WEEKDAYS, spoken_list};
+use crate::timers::{Timer, TimerService};
+use text2num::{Language, replace_numbers_in_text};
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, TimeZone, Timelike};
+use json;
+use json::JsonValue;
+
+// where reminders are kept, so they still go off after a restart
+const REMINDERS_FILE : &str = "reminders.json";
+// the time used when only a day is given, like "remind me tomorrow to call mom"
+const DEFAULT_HOUR : u32 = 9;
+// the same, for "tomorrow afternoon" and for "this evening" or "tonight"
+const DEFAULT_AFTERNOON_HOUR : u32 = 14;
+const DEFAULT_EVENING_HOUR : u32 = 19;
+// words for part of the day, which say whether a time is in the morning or afternoon
+const MORNING_WORDS : [&str; 3] = ["morning", "am", "a.m."];
+const EVENING_WORDS : [&str; 7] = ["afternoon", "evening", "night", "tonight", "pm", "p.m.", "p"];
+
+struct Reminder {
+    task : String, // what to be reminded of, like "call mom"
+    due : DateTime<Local>,
+}
+
+// what a reminder is still missing, while asking for it
+enum Missing {
+    Task(DateTime<Local>),
+    Time(String),
+}
+
+fn load(storage : &Storage) -> Vec<Reminder> {
+    let mut reminders = Vec::new();
+    if let Some(saved) = storage.load(REMINDERS_FILE) {
+        for entry in saved["reminders"].members() {
+            let due = entry["due"].as_i64().and_then(|t| Local.timestamp_opt(t, 0).single());
+            if let (Some(task), Some(due)) = (entry["task"].as_str(), due) {
+                reminders.push(Reminder{task : String::from(task), due});
+            }
+        }
+    }
+    return reminders;
+}
+
+fn save(storage : &Storage, reminders : &[Reminder]) {
+    let list : Vec<JsonValue> = reminders.iter().map(|r| json::object!{
+        task : r.task.clone(),
+        due : r.due.timestamp(),
+    }).collect();
+    if let Err(e) = storage.save(REMINDERS_FILE, &json::object!{reminders : list}) {
+        eprintln!("Error saving reminders: {}", e);
+    }
+}
+
+// "to call mom", or "that the store closes early", depending on how it was said
+fn phrase(task : &str) -> String {
+    if task.starts_with("that ") || task.starts_with("about ") {
+        return String::from(task);
+    }
+    return format!("to {}", task);
+}
+
+// when a reminder is due, the way it would be said from now, like "tomorrow at 6 PM"
+fn spoken_due(due : DateTime<Local>, now : DateTime<Local>) -> String {
+    let time = spoken_time(due.time());
+    let days = (due.date_naive() - now.date_naive()).num_days();
+    return match days {
+        0 => {format!("at {}", time)},
+        1 => {format!("tomorrow at {}", time)},
+        2..=6 => {format!("on {} at {}", due.format("%A"), time)},
+        _ => {format!("on {} at {}", due.format("%B %-d"), time)},
+    };
+}
+
+// reminds you of things at a time of day, or after a while, and keeps them across restarts
+pub struct ReminderCommand {
+    timers : TimerService,
+    storage : Storage,
+}
+impl ReminderCommand {
+    pub fn new(timers : TimerService, storage : Storage) -> ReminderCommand {
+        let command = ReminderCommand{timers, storage};
+        let now = Local::now();
+        let mut reminders = load(&command.storage);
+        // anything that came due while this wasn't running is said right away, then forgotten
+        for reminder in reminders.iter().filter(|r| r.due <= now) {
+            command.timers.start(Timer::reminder(&reminder.task, now,
+                &format!("Earlier, you asked me to remind you {}.", phrase(&reminder.task))));
+        }
+        reminders.retain(|r| r.due > now);
+        save(&command.storage, &reminders);
+        for reminder in &reminders {
+            command.schedule(reminder);
+        }
+        return command;
+    }
+
+    // sets a timer to go off when the reminder is due, which forgets the reminder afterwards
+    fn schedule(&self, reminder : &Reminder) {
+        let storage = self.storage.clone();
+        let timer = Timer::reminder(&reminder.task, reminder.due, &format!("Reminder: {}.", reminder.task)).then(move || {
+            let mut reminders = load(&storage);
+            let now = Local::now();
+            reminders.retain(|r| r.due > now);
+            save(&storage, &reminders);
+        });
+        self.timers.start(timer);
+    }
+
+    fn add(&self, reminder : Reminder) -> String {
+        let answer = format!("Okay, I'll remind you {} {}.", phrase(&reminder.task), spoken_due(reminder.due, Local::now()));
+        self.schedule(&reminder);
+        let mut reminders = load(&self.storage);
+        reminders.push(reminder);
+        reminders.sort_by_key(|r| r.due);
+        save(&self.storage, &reminders);
+        return answer;
+    }
+
+    // pulls the time out of what was said, giving back when it is and the words that are left over
+    // understands "in twenty minutes", "at six thirty pm", "tomorrow at nine", "on friday", and "at noon"
+    fn parse_time(text : &str, now : DateTime<Local>) -> (Option<DateTime<Local>>, String) {
+        let converted = replace_numbers_in_text(text, &Language::english(), 0.0);
+        let words : Vec<&str> = converted.split_whitespace().collect();
+        let mut used = vec![false; words.len()];
+        let number = |i : usize| words.get(i).and_then(|w| {
+            if *w == "a" || *w == "an" {Some(1)} else {w.parse::<u64>().ok()}
+        });
+
+        // a length of time from now, like "in an hour and five minutes"
+        if let Some(start) = (0..words.len()).find(|i| words[*i] == "in" && number(i + 1).is_some()
+            && words.get(i + 2).is_some_and(|w| unit_millis(w).is_some())) {
+            let mut millis = 0;
+            let mut end = start + 1;
+            while let (Some(n), Some(unit)) = (number(end), words.get(end + 1).and_then(|w| unit_millis(w))) {
+                millis += n * unit;
+                end += 2;
+                if words.get(end) == Some(&"and") && number(end + 1).is_some() {
+                    end += 1;
+                }
+            }
+            used[start..end].iter_mut().for_each(|u| *u = true);
+            let due = now + chrono::Duration::milliseconds(millis as i64);
+            return (Some(due), ReminderCommand::leftover(&words, &used));
+        }
+
+        // the day
+        let mut date = None;
+        for (i, word) in words.iter().enumerate() {
+            let ahead = if *word == "tomorrow" {
+                Some(1)
+            } else if *word == "today" || *word == "tonight" {
+                Some(0)
+            } else {
+                WEEKDAYS.iter().find(|(name, _)| name == word).map(|(_, weekday)| {
+                    // the next one, not today
+                    let ahead = (weekday.num_days_from_monday() + 7 - now.weekday().num_days_from_monday()) % 7;
+                    if ahead == 0 {7} else {ahead}
+                })
+            };
+            if let Some(ahead) = ahead {
+                date = Some(now.date_naive() + Days::new(ahead as u64));
+                used[i] = true;
+                if i > 0 && (words[i - 1] == "on" || words[i - 1] == "this") {
+                    used[i - 1] = true;
+                }
+            }
+        }
+
+        // the time of day
+        let mut time = None;
+        let mut evening = None;
+        for i in 0..words.len() {
+            if words[i] != "at" && words[i] != "by" {
+                continue;
+            }
+            let (hour, minute, end) = match (words.get(i + 1).copied(), number(i + 1)) {
+                (Some("noon"), _) => {(12, 0, i + 2)},
+                (Some("midnight"), _) => {(0, 0, i + 2)},
+                (_, Some(hour)) if (1..=12).contains(&hour) && words[i + 1] != "a" && words[i + 1] != "an" => {
+                    match (words.get(i + 2).copied(), number(i + 2), number(i + 3)) {
+                        (Some("oh"), _, Some(m)) if m < 10 => {(hour, m, i + 4)},
+                        (_, Some(m), _) if m < 60 && words[i + 2] != "a" && words[i + 2] != "an" => {(hour, m, i + 3)},
+                        (Some("o'clock"), _, _) => {(hour, 0, i + 3)},
+                        _ => {(hour, 0, i + 2)},
+                    }
+                },
+                _ => {continue},
+            };
+            used[i..end].iter_mut().for_each(|u| *u = true);
+            time = Some((hour as u32, minute as u32, words[i + 1] == "noon" || words[i + 1] == "midnight"));
+            // "pm", "p m", or "in the evening" after it
+            let mut j = end;
+            if words.get(j) == Some(&"in") && words.get(j + 1) == Some(&"the") {
+                j += 2;
+            }
+            if let Some(w) = words.get(j) {
+                if MORNING_WORDS.contains(w) || *w == "a" && words.get(j + 1) == Some(&"m") {
+                    evening = Some(false);
+                } else if EVENING_WORDS.contains(w) && (*w != "p" || words.get(j + 1) == Some(&"m")) {
+                    evening = Some(true);
+                }
+                if evening.is_some() {
+                    let last = if *w == "a" || *w == "p" {j + 1} else {j};
+                    used[end..=last].iter_mut().for_each(|u| *u = true);
+                }
+            }
+            break;
+        }
+        // a part of the day right after the day, like "tomorrow afternoon" or "this evening", is part of the time too
+        for i in 1..words.len() {
+            if (MORNING_WORDS[0] == words[i] || EVENING_WORDS[..3].contains(&words[i])) && (used[i - 1] || words[i - 1] == "this") {
+                if words[i - 1] == "this" {
+                    date = date.or(Some(now.date_naive()));
+                }
+                used[i - 1] = true;
+                used[i] = true;
+                evening = evening.or(Some(words[i] != MORNING_WORDS[0]));
+            }
+        }
+        if evening.is_none() && words.iter().any(|w| EVENING_WORDS[..4].contains(w)) {
+            evening = Some(true);
+        }
+
+        let (hour, minute) = match time {
+            Some((hour, minute, true)) => {(hour, minute)},
+            Some((hour, minute, false)) => {
+                let hour = hour % 12;
+                match evening {
+                    Some(true) => {(hour + 12, minute)},
+                    Some(false) => {(hour, minute)},
+                    // hardly anyone means one to six in the morning
+                    None if (1..=6).contains(&hour) => {(hour + 12, minute)},
+                    // otherwise it's whichever comes next today, the morning or the evening
+                    None if date.is_none() => {
+                        let later = |h : u32| now.date_naive().and_hms_opt(h, minute, 0)
+                            .and_then(|t| Local.from_local_datetime(&t).earliest()).is_some_and(|t| t > now);
+                        if later(hour) || !later(hour + 12) {(hour, minute)} else {(hour + 12, minute)}
+                    },
+                    None => {(hour, minute)},
+                }
+            },
+            None if date.is_some() && evening == Some(true) => {
+                let hour = if words.contains(&"afternoon") {DEFAULT_AFTERNOON_HOUR} else {DEFAULT_EVENING_HOUR};
+                // "tonight" after seven still means tonight, so it's the next hour instead of tomorrow night
+                if date == Some(now.date_naive()) && now.hour() >= hour {
+                    if now.hour() < 23 {(now.hour() + 1, 0)} else {(23, 59)}
+                } else {
+                    (hour, 0)
+                }
+            },
+            None if date.is_some() => {(DEFAULT_HOUR, 0)},
+            None => {return (None, ReminderCommand::leftover(&words, &used))},
+        };
+        let mut day = date.unwrap_or(now.date_naive());
+        let mut due = NaiveTime::from_hms_opt(hour, minute, 0).and_then(|t| Local.from_local_datetime(&day.and_time(t)).earliest());
+        // a time that's already gone today means tomorrow
+        if date.is_none_or(|d| d == now.date_naive()) && due.is_some_and(|d| d <= now) {
+            day = day + Days::new(1);
+            due = NaiveTime::from_hms_opt(hour, minute, 0).and_then(|t| Local.from_local_datetime(&day.and_time(t)).earliest());
+        }
+        return (due, ReminderCommand::leftover(&words, &used));
+    }
+
+    // the words that weren't part of the time, which say what to be reminded of
+    fn leftover(words : &[&str], used : &[bool]) -> String {
+        let left : Vec<&str> = words.iter().zip(used).filter(|(_, u)| !**u).map(|(w, _)| *w).collect();
+        let mut task = left.join(" ");
+        if let Some((_, after)) = task.split_once("remind me") {
+            task = String::from(after);
+        }
+        let task = task.trim().trim_end_matches("please").trim();
+        return String::from(task.strip_prefix("to ").unwrap_or(task));
+    }
+
+    fn list(&self) -> String {
+        let now = Local::now();
+        let reminders : Vec<Reminder> = load(&self.storage).into_iter().filter(|r| r.due > now).collect();
+        if reminders.is_empty() {
+            return String::from("You don't have any reminders.");
+        }
+        let described : Vec<String> = reminders.iter().map(|r| format!("{} {}", phrase(&r.task), spoken_due(r.due, now))).collect();
+        if described.len() == 1 {
+            return format!("You have one reminder, {}.", described[0]);
+        }
+        return format!("You have {} reminders: {}.", described.len(), spoken_list(&described));
+    }
+
+    fn delete(&self, text : &str) -> String {
+        let now = Local::now();
+        let mut reminders : Vec<Reminder> = load(&self.storage).into_iter().filter(|r| r.due > now).collect();
+        if reminders.is_empty() {
+            return String::from("You don't have any reminders.");
+        }
+        let everything = text.contains(" all ") || text.contains("every");
+        let query = text.split_once("reminder").map(|(_, after)| after.trim_start_matches('s').trim()).unwrap_or("");
+        let query = ["to ", "about ", "that ", "for "].iter().fold(query, |q, p| q.strip_prefix(p).unwrap_or(q)).trim();
+        let matches = |r : &Reminder| everything || (query.is_empty() && reminders.len() == 1)
+            || (!query.is_empty() && (r.task.contains(query) || query.contains(r.task.as_str())));
+        let gone : Vec<String> = reminders.iter().filter(|r| matches(r)).map(|r| r.task.clone()).collect();
+        if gone.is_empty() {
+            if query.is_empty() {
+                return format!("Which reminder? {}", self.list());
+            }
+            return format!("I couldn't find a reminder about {}.", query);
+        }
+        reminders.retain(|r| !gone.contains(&r.task));
+        save(&self.storage, &reminders);
+        self.timers.lock().retain(|t| !(t.is_reminder() && gone.contains(&t.name)));
+        if gone.len() == 1 {
+            return format!("Okay, I've deleted your reminder {}.", phrase(&gone[0]));
+        }
+        return format!("Okay, I've deleted {} reminders.", gone.len());
+    }
+}
+impl Command for ReminderCommand {
+    fn name(&self) -> String {
+        return String::from("Reminder");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command reminds you of things at a time you choose, even after a restart.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"remind me\", what to be reminded of, and when, like \"remind me to call mom at six\" or \"remind me in twenty minutes to check the oven\". You can also say a day, like \"tomorrow\" or \"on friday\". Reminders stay on screen until you dismiss them. Say \"what are my reminders\" to hear them, and \"delete my reminder to\" something, or \"delete all my reminders\", to take them away.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("reminders"), String::from("remind me")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("remind me to call mom at six"), String::from("remind me in twenty minutes to check the oven"),
+            String::from("what are my reminders"), String::from("delete my reminder to call mom")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        return text.contains("remind me") || text.contains("reminder");
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let words : Vec<&str> = text.split_whitespace().collect();
+        let answer = if ["delete", "cancel", "remove", "clear", "forget"].iter().any(|w| words.contains(w)) && !text.contains("remind me") {
+            self.delete(&text)
+        } else if !text.contains("remind me") {
+            self.list()
+        } else {
+            match ReminderCommand::parse_time(&text, Local::now()) {
+                (Some(due), task) if !task.is_empty() => {self.add(Reminder{task, due})},
+                (Some(due), _) => {
+                    speak.send(SpeakMessage::Say(String::from("What should I remind you about?"))).unwrap();
+                    return CommandResult::Continue(Box::new(Missing::Task(due)));
+                },
+                (None, task) if !task.is_empty() => {
+                    speak.send(SpeakMessage::Say(String::from("When should I remind you?"))).unwrap();
+                    return CommandResult::Continue(Box::new(Missing::Time(task)));
+                },
+                (None, _) => {String::from("I didn't catch what to remind you of, or when.")},
+            }
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+    fn resume(&mut self, state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let missing = match state.downcast::<Missing>() {
+            Ok(m) => {*m},
+            Err(_) => {return CommandResult::Done},
+        };
+        let answer = match missing {
+            Missing::Task(due) => {
+                let task = text.trim();
+                self.add(Reminder{task : String::from(task.strip_prefix("to ").unwrap_or(task)), due})
+            },
+            Missing::Time(task) => {
+                // an answer like "six thirty" is taken as a time of day
+                let (due, _) = ReminderCommand::parse_time(&text, Local::now());
+                let due = due.or_else(|| ReminderCommand::parse_time(&format!("at {}", text), Local::now()).0);
+                match due {
+                    Some(due) => {self.add(Reminder{task, due})},
+                    None => {
+                        speak.send(SpeakMessage::Say(String::from("I didn't catch a time. Try something like \"at six\" or \"in twenty minutes\"."))).unwrap();
+                        return CommandResult::Continue(Box::new(Missing::Time(task)));
+                    },
+                }
+            },
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+}
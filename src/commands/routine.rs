@@ -0,0 +1,270 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{Sender};
+use crate::{Earcon, SpeakMessage};
+use crate::commands::alarm::unit_millis;
+use crate::timers::{Step, Timer, TimerKind, TimerService, spoken_duration};
+use text2num::{Language, replace_numbers_in_text};
+use std::time::Duration;
+
+// how many rounds to do when it isn't said
+const DEFAULT_ROUNDS : usize = 4;
+// the most rounds a routine can have, so a misheard number doesn't run all day
+const MAX_ROUNDS : usize = 50;
+// words for the working part of a round, and the resting part
+const WORK_WORDS : [&str; 6] = ["work", "working", "focus", "exercise", "on", "active"];
+const BREAK_WORDS : [&str; 6] = ["break", "breaks", "rest", "resting", "off", "recover"];
+const ROUND_WORDS : [&str; 9] = ["rounds", "round", "cycles", "cycle", "sets", "times", "intervals", "reps", "laps"];
+// words that start a routine
+const START_WORDS : [&str; 5] = ["pomodoro", "tabata", "routine", "interval", "intervals"];
+
+// what can be said to a routine that's going without saying which routine
+const BARE_PHRASES : [(&str, Action); 14] = [("pause", Action::Pause), ("hold on", Action::Pause), ("resume", Action::Resume),
+    ("unpause", Action::Resume), ("continue", Action::Resume), ("keep going", Action::Resume), ("skip", Action::Skip),
+    ("skip this round", Action::Skip), ("skip the break", Action::Skip), ("how long is left", Action::Check),
+    ("how much time is left", Action::Check), ("how much longer", Action::Check), ("what round is", Action::Check),
+    ("what round are we on", Action::Check)];
+
+// a routine that's always the same, like a pomodoro
+struct Preset {
+    word : &'static str,
+    name : &'static str,
+    rounds : usize,
+    work : Duration,
+    rest : Duration,
+}
+
+const PRESETS : [Preset; 2] = [
+    Preset{word : "pomodoro", name : "pomodoro", rounds : 4, work : Duration::from_secs(25 * 60), rest : Duration::from_secs(5 * 60)},
+    Preset{word : "tabata", name : "workout", rounds : 8, work : Duration::from_secs(20), rest : Duration::from_secs(10)},
+];
+
+// what was asked of a routine
+#[derive(Clone, Copy)]
+enum Action {
+    Start,
+    Pause,
+    Resume,
+    Skip,
+    Stop,
+    Check,
+}
+
+// runs rounds of work and rest, like a pomodoro or workout intervals, saying when each part starts
+pub struct RoutineCommand {
+    timers : TimerService,
+}
+impl RoutineCommand {
+    pub fn new(timers : TimerService) -> RoutineCommand {
+        return RoutineCommand{timers};
+    }
+
+    fn running(&self) -> bool {
+        return self.timers.lock().iter().any(|t| t.is_routine());
+    }
+
+    fn parse_action(&self, text : &str) -> Option<Action> {
+        let words : Vec<&str> = text.split_whitespace().collect();
+        let has = |list : &[&str]| list.iter().any(|w| words.contains(w));
+        let named = has(&START_WORDS) || text.contains("workout");
+        // without the routine being mentioned, only short phrases like "pause" are taken, so "stop the stopwatch" isn't
+        if !named {
+            if !self.running() {
+                return if has(&ROUND_WORDS) && has(&WORK_WORDS) {Some(Action::Start)} else {None};
+            }
+            let trimmed = text.trim().trim_end_matches(" please").trim_end_matches(" it");
+            return BARE_PHRASES.iter().find(|(phrase, _)| *phrase == trimmed).map(|(_, action)| *action);
+        }
+        if has(&["stop", "end", "quit", "cancel"]) {
+            return Some(Action::Stop);
+        }
+        if has(&["pause", "hold"]) {
+            return Some(Action::Pause);
+        }
+        if has(&["resume", "unpause", "continue"]) || text.contains("keep going") {
+            return Some(Action::Resume);
+        }
+        if has(&["skip"]) || text.contains("next step") {
+            return Some(Action::Skip);
+        }
+        if text.contains("how long") || text.contains("how much") || text.contains("what round") || text.contains("left") {
+            return Some(Action::Check);
+        }
+        return Some(Action::Start);
+    }
+
+    // the length of one part of a round, from "25 minutes work", "25 work", or "work for 25 minutes"
+    // a number without a unit is taken as minutes
+    fn length_at(words : &[&str], index : usize, after : bool) -> Option<Duration> {
+        let number = |i : usize| words.get(i).and_then(|w| w.parse::<u64>().ok());
+        let unit = |i : usize| words.get(i).and_then(|w| unit_millis(w)).unwrap_or(60000);
+        let millis = if after {
+            let start = if words.get(index + 1).is_some_and(|w| *w == "for") {index + 2} else {index + 1};
+            number(start)? * unit(start + 1)
+        } else if index >= 2 && words.get(index - 1).is_some_and(|w| unit_millis(w).is_some()) {
+            number(index - 2)? * unit(index - 1)
+        } else {
+            number(index.checked_sub(1)?)? * 60000
+        };
+        return Some(Duration::from_millis(millis));
+    }
+
+    // the name, rounds, work, and rest of a routine, from a preset or from what was said
+    fn parse_routine(text : &str) -> (String, usize, Duration, Duration) {
+        let converted = replace_numbers_in_text(text, &Language::english(), 0.0);
+        let words : Vec<&str> = converted.split_whitespace().collect();
+        let (mut name, mut rounds, mut work, mut rest) = match PRESETS.iter().find(|p| words.contains(&p.word)) {
+            Some(p) => {(String::from(p.name), p.rounds, p.work, p.rest)},
+            None => {(String::from("routine"), DEFAULT_ROUNDS, Duration::ZERO, Duration::ZERO)},
+        };
+        if text.contains("workout") || text.contains("interval") {
+            name = String::from("workout");
+        }
+        if let Some(n) = words.iter().position(|w| ROUND_WORDS.contains(w)).and_then(|i| words.get(i.checked_sub(1)?)?.parse::<usize>().ok()) {
+            rounds = n.clamp(1, MAX_ROUNDS);
+        }
+        let work_at = words.iter().position(|w| WORK_WORDS.contains(w));
+        let rest_at = words.iter().position(|w| BREAK_WORDS.contains(w));
+        // if the first part has its length after it, like "work 25 minutes break 5", they all do
+        let first = match (work_at, rest_at) {
+            (Some(w), Some(r)) => {Some(w.min(r))},
+            (w, r) => {w.or(r)},
+        };
+        let is_length = |w : &&str| w.parse::<u64>().is_ok() || unit_millis(w).is_some();
+        let after = first.is_some_and(|i| !(i > 0 && is_length(&words[i - 1]))
+            && words.get(i + 1).is_some_and(|w| *w == "for" || w.parse::<u64>().is_ok()));
+        if let Some(length) = work_at.and_then(|i| RoutineCommand::length_at(&words, i, after)) {
+            work = length;
+        }
+        if let Some(length) = rest_at.and_then(|i| RoutineCommand::length_at(&words, i, after)) {
+            rest = length;
+        }
+        return (name, rounds, work, rest);
+    }
+
+    fn steps(rounds : usize, work : Duration, rest : Duration) -> Vec<Step> {
+        let mut steps = Vec::new();
+        for round in 1..=rounds {
+            steps.push(Step{name : String::from("work"), round, length : work,
+                message : format!("Round {} of {}. Work for {}.", round, rounds, spoken_duration(work))});
+            if round < rounds && !rest.is_zero() {
+                steps.push(Step{name : String::from("break"), round, length : rest,
+                    message : format!("Take a break for {}.", spoken_duration(rest))});
+            }
+        }
+        return steps;
+    }
+
+    fn start(&self, text : &str, speak : &Sender<SpeakMessage>) -> String {
+        if let Some(name) = self.timers.lock().iter().find(|t| t.is_routine()).map(|t| t.name.clone()) {
+            return format!("You already have a {} going. Say \"stop the {}\" first to start a new one.", name, name);
+        }
+        let (name, rounds, work, rest) = RoutineCommand::parse_routine(text);
+        if work.is_zero() {
+            return String::from("I didn't catch how long each round should be. Try something like \"four rounds of twenty five minutes work and five minutes break\".");
+        }
+        let steps = RoutineCommand::steps(rounds, work, rest);
+        let first = steps[0].message.clone();
+        let plan = if rest.is_zero() {
+            format!("Starting a {} of {} rounds of {}.", name, rounds, spoken_duration(work))
+        } else {
+            format!("Starting a {} of {} rounds, with {} of work and {} of rest.", name, rounds, spoken_duration(work), spoken_duration(rest))
+        };
+        self.timers.start(Timer::routine(&name, steps, &format!("That's the end of your {}. Nice work.", name)));
+        speak.send(SpeakMessage::Earcon(Earcon::Begin)).unwrap();
+        return format!("{} {}", plan, first);
+    }
+
+    // where the routine is up to, like "Round 2 of 4, work, with 12 minutes left."
+    fn describe(timer : &Timer) -> String {
+        if let TimerKind::Routine{steps, step, ..} = &timer.kind {
+            let rounds = steps.last().map(|s| s.round).unwrap_or(0);
+            let current = &steps[*step];
+            let paused = if timer.is_paused() {" It's paused."} else {""};
+            return format!("Round {} of {}, {}, with {} left.{}", current.round, rounds, current.name,
+                spoken_duration(timer.remaining().unwrap_or_default()), paused);
+        }
+        return String::new();
+    }
+}
+impl Command for RoutineCommand {
+    fn name(&self) -> String {
+        return String::from("Routine");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command runs rounds of work and rest, like a pomodoro or workout intervals.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"start a pomodoro\" for four rounds of twenty five minutes of work and five minutes of break, or \"start a tabata\" for eight rounds of twenty seconds of work and ten seconds of rest. For your own, say the rounds and lengths, like \"four rounds of twenty five work five break\" or \"start a workout of ten rounds of thirty seconds on and fifteen seconds off\". A number without seconds or minutes is taken as minutes. While it's going, say \"pause\", \"resume\", \"skip\", \"how long is left\", or \"stop the routine\".");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("pomodoro"), String::from("intervals"), String::from("tabata"), String::from("workout")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("start a pomodoro"), String::from("four rounds of twenty five work five break"),
+            String::from("pause the pomodoro"), String::from("how long is left")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        return self.parse_action(&text).is_some();
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let action = match self.parse_action(&text) {
+            Some(a) => {a},
+            None => {return CommandResult::Done},
+        };
+        if let Action::Start = action {
+            let answer = self.start(&text, &speak);
+            speak.send(SpeakMessage::Say(answer)).unwrap();
+            return CommandResult::Done;
+        }
+        let mut timers = self.timers.lock();
+        let index = match timers.iter().position(|t| t.is_routine()) {
+            Some(i) => {i},
+            None => {
+                speak.send(SpeakMessage::Say(String::from("There's no routine going. Say \"start a pomodoro\" to start one."))).unwrap();
+                return CommandResult::Done;
+            },
+        };
+        let mut said = Vec::new();
+        match action {
+            Action::Stop => {
+                let timer = timers.remove(index);
+                said.push(SpeakMessage::Say(format!("Okay, I've stopped your {}.", timer.name)));
+            },
+            Action::Pause => {
+                let timer = &mut timers[index];
+                let answer = if timer.pause() {
+                    format!("Paused, with {} left.", spoken_duration(timer.remaining().unwrap_or_default()))
+                } else {
+                    String::from("It's already paused. Say \"resume\" to keep going.")
+                };
+                said.push(SpeakMessage::Say(answer));
+            },
+            Action::Resume => {
+                let timer = &mut timers[index];
+                let answer = if timer.resume() {
+                    format!("Resuming, with {} left.", spoken_duration(timer.remaining().unwrap_or_default()))
+                } else {
+                    String::from("It's not paused.")
+                };
+                said.push(SpeakMessage::Say(answer));
+            },
+            Action::Skip => {
+                let (running, announcements) = timers[index].advance();
+                if !running {
+                    timers.remove(index);
+                }
+                said.extend(announcements);
+            },
+            _ => {said.push(SpeakMessage::Say(RoutineCommand::describe(&timers[index])))},
+        }
+        // the timers aren't needed while talking
+        drop(timers);
+        for message in said {
+            speak.send(message).unwrap();
+        }
+        return CommandResult::Done;
+    }
+}
@@ -0,0 +1,257 @@
+use crate::commands::{Command, CommandResult, SessionState};
+use std::sync::mpsc::{self, Sender};
+use crate::SpeakMessage;
+use crate::commands::custom::{get_string, get_string_list, get_bool};
+use crate::commands::spoken::is_yes;
+use std::fs;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use toml;
+
+// how long a program gets to run if its action doesn't say
+pub const DEFAULT_TIMEOUT : Duration = Duration::from_secs(10);
+// output longer than this gets cut off instead of being read out in full
+const MAX_SPOKEN_OUTPUT : usize = 400;
+// how long to keep reading output after the program exits, in case something it started still has the pipe open
+const OUTPUT_GRACE : Duration = Duration::from_millis(500);
+
+// one thing the shell command can do
+struct ShellAction {
+    triggers : Vec<String>,
+    argv : Vec<String>, // a program and its arguments, passed to "sh -c" instead if shell is set
+    shell : bool,
+    confirm : bool,
+    read_output : bool,
+    response : String,
+    timeout : Duration,
+}
+
+// runs local programs when their trigger phrases are heard
+pub struct ShellCommand {
+    actions : Vec<ShellAction>,
+}
+impl ShellCommand {
+    // loads the actions from a TOML file, returning None if there aren't any
+    pub fn load(path : &str) -> Option<ShellCommand> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => {c},
+            Err(_) => {return None}, // no file just means no shell actions
+        };
+        match ShellCommand::parse(&contents) {
+            Ok(c) => {
+                if c.actions.is_empty() {
+                    return None;
+                }
+                println!("Loaded {} shell actions from {}", c.actions.len(), path);
+                return Some(c);
+            },
+            Err(e) => {
+                eprintln!("Error loading shell actions from {}: {}", path, e);
+                return None;
+            }
+        }
+    }
+
+    fn parse(contents : &str) -> Result<ShellCommand, String> {
+        let table = contents.parse::<toml::Table>().map_err(|e| e.to_string())?;
+        let mut actions = Vec::new();
+        let list = match table.get("action") {
+            Some(v) => {v.as_array().ok_or("\"action\" must be a list of tables, use [[action]]")?.clone()},
+            None => {Vec::new()},
+        };
+        for (i, item) in list.iter().enumerate() {
+            let action = item.as_table().ok_or(format!("action {} is not a table", i + 1))?;
+            actions.push(ShellAction::parse(action).map_err(|e| format!("action {}: {}", i + 1, e))?);
+        }
+        return Ok(ShellCommand{actions});
+    }
+
+    // finds the action with a trigger phrase in the text
+    fn find(&self, text : &str) -> Option<usize> {
+        return self.actions.iter().position(|a| a.triggers.iter().any(|t| text.contains(t.as_str())));
+    }
+
+    // runs an action and says how it went
+    fn run(&self, index : usize, speak : &Sender<SpeakMessage>) {
+        let action = &self.actions[index];
+        let result = if action.shell {
+            run_program(&[String::from("sh"), String::from("-c"), action.argv.join(" ")], action.timeout)
+        } else {
+            run_program(&action.argv, action.timeout)
+        };
+        match result {
+            Ok(output) => {
+                let mut answer = action.response.clone();
+                if action.read_output && !output.is_empty() {
+                    let mut output = output;
+                    if output.len() > MAX_SPOKEN_OUTPUT {
+                        let mut end = MAX_SPOKEN_OUTPUT;
+                        while !output.is_char_boundary(end) {
+                            end -= 1;
+                        }
+                        output.truncate(end);
+                        output.push_str(", and so on.");
+                    }
+                    answer = format!("{} {}", answer, output);
+                }
+                speak.send(SpeakMessage::Say(answer)).unwrap();
+            },
+            Err(e) => {
+                speak.send(SpeakMessage::Say(e)).unwrap();
+            }
+        }
+    }
+}
+impl ShellAction {
+    fn parse(table : &toml::Table) -> Result<ShellAction, String> {
+        let triggers = get_string_list(table, "triggers")?.unwrap_or_default();
+        if triggers.is_empty() {
+            return Err(String::from("\"triggers\" is missing or empty"));
+        }
+        let argv = get_string_list(table, "run")?.unwrap_or_default();
+        if argv.is_empty() {
+            return Err(String::from("\"run\" is missing or empty"));
+        }
+        let timeout = match table.get("timeout") {
+            Some(v) => {Duration::from_secs(v.as_integer().filter(|t| *t > 0).ok_or("\"timeout\" must be a number of seconds")? as u64)},
+            None => {DEFAULT_TIMEOUT},
+        };
+        return Ok(ShellAction {
+            triggers : triggers.iter().map(|t| t.to_lowercase()).collect(),
+            argv,
+            shell : get_bool(table, "shell")?.unwrap_or(false),
+            confirm : get_bool(table, "confirm")?.unwrap_or(false),
+            read_output : get_bool(table, "read_output")?.unwrap_or(false),
+            response : get_string(table, "response")?.unwrap_or(String::from("Done.")),
+            timeout,
+        });
+    }
+}
+impl Command for ShellCommand {
+    fn name(&self) -> String {
+        return String::from("Shell Command");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command runs programs on this computer.");
+    }
+    fn help(&self) -> String {
+        let triggers : Vec<String> = self.actions.iter().map(|a| a.triggers[0].clone()).collect();
+        return format!("Say one of these phrases to run its program: {}.", triggers.join(", "));
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("shell"), String::from("programs"), String::from("scripts")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return self.actions.iter().map(|a| a.triggers[0].clone()).collect();
+    }
+    fn runs_long(&self) -> bool {
+        return true;
+    }
+    fn recognize(&self, text : String) -> bool {
+        return self.find(&text).is_some();
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let index = match self.find(&text) {
+            Some(i) => {i},
+            None => {return CommandResult::Done},
+        };
+        if self.actions[index].confirm {
+            speak.send(SpeakMessage::Say(format!("Are you sure you want me to {}?", self.actions[index].triggers[0]))).unwrap();
+            return CommandResult::Continue(Box::new(index));
+        }
+        self.run(index, &speak);
+        return CommandResult::Done;
+    }
+    fn resume(&mut self, state : SessionState, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let index = match state.downcast::<usize>() {
+            Ok(i) => {*i},
+            Err(_) => {return CommandResult::Done},
+        };
+        if is_yes(&text) {
+            self.run(index, &speak);
+        } else {
+            speak.send(SpeakMessage::Say(String::from("Okay, I won't."))).unwrap();
+        }
+        return CommandResult::Done;
+    }
+}
+
+// runs a program directly (no shell), giving back its trimmed output, and stops it if it runs too long
+// it gets its own process group, so anything it starts is stopped along with it
+pub fn run_program(argv : &[String], timeout : Duration) -> Result<String, String> {
+    let mut child = process::Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .or(Err(format!("I couldn't run {}.", argv[0])))?;
+    // read the output on another thread so a chatty program can't fill the pipe and get stuck
+    // it's sent over in pieces, since something the program started (like a browser) can keep the pipe open long after it's done
+    let mut stdout = child.stdout.take().unwrap();
+    let (outputtx, outputrx) = mpsc::channel::<Vec<u8>>();
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        loop {
+            match stdout.read(&mut buffer) {
+                Ok(0) | Err(_) => {break},
+                Ok(n) => {
+                    if outputtx.send(buffer[..n].to_vec()).is_err() {
+                        break;
+                    }
+                },
+            }
+        }
+    });
+    let mut output = Vec::new();
+    let start = Instant::now();
+    let status = loop {
+        while let Ok(piece) = outputrx.try_recv() {
+            output.extend(piece);
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => {break status},
+            Ok(None) => {
+                if start.elapsed() > timeout {
+                    kill_group(&mut child);
+                    return Err(format!("{} took too long, so I stopped it.", argv[0]));
+                }
+                thread::sleep(Duration::from_millis(50));
+            },
+            Err(_) => {
+                kill_group(&mut child);
+                return Err(format!("I lost track of {}.", argv[0]));
+            },
+        }
+    };
+    // get whatever's left, without waiting on anything still holding the pipe after the program's done
+    let deadline = Instant::now() + OUTPUT_GRACE;
+    loop {
+        match outputrx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(piece) => {output.extend(piece)},
+            Err(_) => {break},
+        }
+    }
+    if !status.success() {
+        return Err(format!("{} didn't finish successfully.", argv[0]));
+    }
+    return Ok(String::from_utf8_lossy(&output).trim().to_string());
+}
+
+// stops a program and everything it started, which are all in the process group named after it
+fn kill_group(child : &mut process::Child) {
+    let group = format!("-{}", child.id());
+    let killed = process::Command::new("kill").args(["-s", "KILL", "--", &group])
+        .stdout(Stdio::null()).stderr(Stdio::null()).status();
+    if !killed.is_ok_and(|s| s.success()) {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+}
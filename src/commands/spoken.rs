@@ -0,0 +1,40 @@
+use chrono::Weekday;
+
+// helpers for understanding and saying things, shared by the commands that need them
+
+// the days of the week as they're said, in the order chrono counts them
+pub const WEEKDAYS : [(&str, Weekday); 7] = [("monday", Weekday::Mon), ("tuesday", Weekday::Tue), ("wednesday", Weekday::Wed),
+    ("thursday", Weekday::Thu), ("friday", Weekday::Fri), ("saturday", Weekday::Sat), ("sunday", Weekday::Sun)];
+
+// answers that count as a yes when asked to confirm something
+pub const YES_WORDS : [&str; 8] = ["yes", "yeah", "yep", "sure", "okay", "do it", "go ahead", "confirm"];
+// words that make an answer a no, even if a yes word is in it too, like "no don't do it" or "yeah no"
+const NO_WORDS : [&str; 6] = ["no", "not", "don't", "dont", "nope", "never"];
+
+// whether an answer to "are you sure" is a yes, which has to start with a yes word and not have a no word anywhere
+pub fn is_yes(text : &str) -> bool {
+    let text = text.trim();
+    if text.split_whitespace().any(|w| NO_WORDS.contains(&w)) {
+        return false;
+    }
+    return YES_WORDS.iter().any(|w| text == *w || text.starts_with(&format!("{} ", w)));
+}
+
+// the text with its first letter made uppercase
+pub fn capitalize(text : &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => {return first.to_uppercase().collect::<String>() + chars.as_str()},
+        None => {return String::new()},
+    }
+}
+
+// a list read out like "3, 5, and 6"
+pub fn spoken_list(items : &[String]) -> String {
+    match items.len() {
+        0 => {return String::new()},
+        1 => {return items[0].clone()},
+        2 => {return format!("{} and {}", items[0], items[1])},
+        n => {return format!("{}, and {}", items[..n - 1].join(", "), items[n - 1])},
+    }
+}
@@ -0,0 +1,203 @@
+use crate::commands::{Command, CommandResult};
+use std::sync::mpsc::{Sender};
+use crate::SpeakMessage;
+use crate::commands::spoken::{capitalize, spoken_list};
+use crate::timers::{Timer, TimerKind, TimerService, spoken_duration};
+use std::time::Duration;
+
+// words around a stopwatch's name that aren't part of it, like "stop the tea stopwatch"
+const NAME_FILLER : [&str; 23] = ["start", "stop", "end", "begin", "restart", "reset", "check", "lap", "the", "a", "an", "my",
+    "new", "another", "on", "of", "for", "is", "has", "long", "how", "time", "what's"];
+
+// what was asked of a stopwatch
+enum Action {
+    Start,
+    Lap,
+    Check,
+    Stop,
+    List,
+}
+
+// keeps time going up, with laps, for as many named stopwatches as are wanted
+pub struct StopwatchCommand {
+    timers : TimerService,
+}
+impl StopwatchCommand {
+    pub fn new(timers : TimerService) -> StopwatchCommand {
+        return StopwatchCommand{timers};
+    }
+
+    fn parse_action(text : &str) -> Option<Action> {
+        let words : Vec<&str> = text.split_whitespace().collect();
+        let stopwatch = words.iter().any(|w| w.starts_with("stopwatch"));
+        if words.contains(&"lap") {
+            return Some(Action::Lap);
+        }
+        if text.contains("how long has it been") && !text.contains("since") {
+            return Some(Action::Check);
+        }
+        if !stopwatch {
+            return None;
+        }
+        if text.contains("what stopwatches") || text.contains("which stopwatches") || text.contains("list") {
+            return Some(Action::List);
+        }
+        if words.contains(&"stop") || words.contains(&"end") || words.contains(&"reset") {
+            return Some(Action::Stop);
+        }
+        if words.contains(&"start") || words.contains(&"begin") || words.contains(&"new") {
+            return Some(Action::Start);
+        }
+        return Some(Action::Check);
+    }
+
+    // the stopwatch's name, like "tea" in "start a stopwatch called tea" or "lap the tea stopwatch"
+    fn parse_name(text : &str) -> String {
+        for marker in [" called ", " named ", " for "] {
+            if let Some((_, after)) = text.split_once(marker) {
+                let name = after.trim().trim_end_matches("stopwatch").trim();
+                return String::from(name.strip_prefix("the ").unwrap_or(name));
+            }
+        }
+        let words : Vec<&str> = text.split_whitespace().collect();
+        let end = match words.iter().position(|w| w.starts_with("stopwatch")) {
+            Some(i) => {i},
+            None => {return String::new()},
+        };
+        let before = &words[..end];
+        let start = before.iter().rposition(|w| NAME_FILLER.contains(w)).map(|i| i + 1).unwrap_or(0);
+        return before[start..].join(" ");
+    }
+
+    fn label(name : &str) -> String {
+        return if name.is_empty() {String::from("the stopwatch")} else {format!("the {} stopwatch", name)};
+    }
+
+    // works out which stopwatch was meant, for anything but starting one
+    // a stopwatch without a name is taken to mean the only one, if there's only one
+    fn pick(&self, name : String) -> Result<String, String> {
+        let names = self.timers.names(true);
+        if names.contains(&name) {
+            return Ok(name);
+        }
+        if !name.is_empty() {
+            return Err(format!("There's no stopwatch called {}.", name));
+        }
+        match names.len() {
+            0 => {return Err(String::from("There's no stopwatch running. Say \"start a stopwatch\" to start one."))},
+            1 => {return Ok(names[0].clone())},
+            _ => {return Err(format!("Which stopwatch? You have {}.", StopwatchCommand::describe_names(&names)))},
+        }
+    }
+
+    fn describe_names(names : &[String]) -> String {
+        let named : Vec<String> = names.iter().map(|n| if n.is_empty() {String::from("one without a name")} else {n.clone()}).collect();
+        return spoken_list(&named);
+    }
+
+    fn start(&self, name : String) -> String {
+        let existing = self.timers.lock().iter().find(|t| t.is_stopwatch() && t.name == name).map(|t| t.elapsed());
+        if let Some(elapsed) = existing {
+            return format!("{} is already running, at {}.", capitalize(&StopwatchCommand::label(&name)), spoken_duration(elapsed));
+        }
+        self.timers.start(Timer::stopwatch(&name));
+        return format!("Okay, {} is started.", StopwatchCommand::label(&name));
+    }
+
+    fn lap(&self, name : String) -> String {
+        let mut timers = self.timers.lock();
+        let timer = match timers.iter_mut().find(|t| t.is_stopwatch() && t.name == name) {
+            Some(t) => {t},
+            None => {return String::new()},
+        };
+        let elapsed = timer.elapsed();
+        if let TimerKind::Stopwatch{laps} = &mut timer.kind {
+            let lap = elapsed - laps.last().copied().unwrap_or_default();
+            laps.push(elapsed);
+            return format!("Lap {}, {}. That's {} in total.", laps.len(), spoken_duration(lap), spoken_duration(elapsed));
+        }
+        return String::new();
+    }
+
+    fn stop(&self, name : String) -> String {
+        let timer = match self.timers.remove(&name, true) {
+            Some(t) => {t},
+            None => {return String::new()},
+        };
+        let elapsed = timer.elapsed();
+        let mut answer = format!("Stopped {} at {}.", StopwatchCommand::label(&name), spoken_duration(elapsed));
+        if let TimerKind::Stopwatch{laps} = timer.kind {
+            if !laps.is_empty() {
+                // the time since the last lap counts as one more
+                let mut times = Vec::new();
+                let mut last = Duration::ZERO;
+                for total in laps.iter().copied().chain([elapsed]) {
+                    times.push(total - last);
+                    last = total;
+                }
+                let (fastest, time) = times.iter().enumerate().min_by_key(|(_, t)| **t).unwrap();
+                answer += &format!(" Your fastest of {} laps was lap {}, at {}.", times.len(), fastest + 1, spoken_duration(*time));
+            }
+        }
+        return answer;
+    }
+}
+impl Command for StopwatchCommand {
+    fn name(&self) -> String {
+        return String::from("Stopwatch");
+    }
+    fn desc(&self) -> String {
+        return String::from("This command times things with stopwatches, with laps.");
+    }
+    fn help(&self) -> String {
+        return String::from("Say \"start a stopwatch\", then \"lap\" to mark a lap, \"how long has it been\" to check it, and \"stop the stopwatch\" to stop it. To run more than one at once, give them names, like \"start a stopwatch called tea\" and \"stop the tea stopwatch\". Say \"list my stopwatches\" to hear which ones are running.");
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("stopwatches"), String::from("lap"), String::from("laps")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("start a stopwatch"), String::from("lap"), String::from("how long has it been"),
+            String::from("stop the stopwatch")];
+    }
+    fn recognize(&self, text : String) -> bool {
+        return StopwatchCommand::parse_action(&text).is_some();
+    }
+    fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
+        let action = match StopwatchCommand::parse_action(&text) {
+            Some(a) => {a},
+            None => {return CommandResult::Done},
+        };
+        let name = StopwatchCommand::parse_name(&text);
+        let answer = match action {
+            Action::Start => {self.start(name)},
+            Action::List => {
+                let names = self.timers.names(true);
+                match names.len() {
+                    0 => {String::from("You don't have any stopwatches running.")},
+                    1 => {format!("You have one stopwatch running, at {}.", spoken_duration(self.timers.lock().iter().find(|t| t.is_stopwatch()).unwrap().elapsed()))},
+                    n => {format!("You have {} stopwatches running: {}.", n, StopwatchCommand::describe_names(&names))},
+                }
+            },
+            _ => {
+                match self.pick(name) {
+                    Err(problem) => {problem},
+                    Ok(name) => {
+                        match action {
+                            Action::Lap => {self.lap(name)},
+                            Action::Stop => {self.stop(name)},
+                            _ => {
+                                let elapsed = self.timers.lock().iter().find(|t| t.is_stopwatch() && t.name == name).map(|t| t.elapsed()).unwrap_or_default();
+                                format!("{} is at {}.", capitalize(&StopwatchCommand::label(&name)), spoken_duration(elapsed))
+                            },
+                        }
+                    },
+                }
+            },
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
+        return CommandResult::Done;
+    }
+}
@@ -16,6 +16,12 @@ impl Command for TestCommand {
     fn uses_internet(&self) -> bool {
         return false;
     }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("testing")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("test command")];
+    }
     fn recognize(&self, text : String) -> bool {
         return text.contains("test command");
     }
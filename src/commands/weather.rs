@@ -1,24 +1,377 @@
 use crate::commands::{Command, CommandResult};
+use std::collections::HashMap;
 use std::sync::mpsc::{Sender};
+use std::time::Duration;
 use crate::SpeakMessage;
-use reqwest;
+use crate::http::HttpService;
+use crate::storage::Storage;
+use crate::config::{Units, WeatherConfig};
+use crate::commands::spoken::{WEEKDAYS, capitalize};
 use json;
+use json::JsonValue;
+
+// the places weather data can come from
+mod wttr;
+use wttr::WttrProvider;
+mod openmeteo;
+use openmeteo::OpenMeteoProvider;
+mod local;
+use local::LocalProvider;
+
+// where the locations set by voice are saved
+const PLACES_FILE : &str = "weather.json";
+// how long a saved forecast is used before asking for a new one
+const FORECAST_MAX_AGE : Duration = Duration::from_secs(15 * 60);
+// how long a looked up location is used, since places don't move
+const PLACE_MAX_AGE : Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// words about when, which shouldn't end up as part of the location
+const TIME_WORDS : [&str; 11] = ["today", "tonight", "tomorrow", "morning", "afternoon", "evening", "night",
+    "weekend", "week", "later", "now"];
+// ways of asking for particular units, which also shouldn't end up as part of the location
+const UNIT_PHRASES : [(&str, Units); 8] = [("in celsius", Units::Metric), ("in centigrade", Units::Metric),
+    ("in metric", Units::Metric), ("in fahrenheit", Units::Imperial), ("in imperial", Units::Imperial),
+    ("celsius", Units::Metric), ("metric", Units::Metric), ("fahrenheit", Units::Imperial)];
+// words that come right before a location
+const PLACE_WORDS : [&str; 4] = ["in", "for", "at", "near"];
+// words that end a location, since what comes after them is part of the rest of the question
+const END_WORDS : [&str; 9] = ["will", "is", "be", "going", "like", "please", "and", "with", "right"];
+// words left over from phrases like "this weekend" or "on monday", which aren't part of a location when they're at the end
+const FILLER_WORDS : [&str; 5] = ["the", "this", "on", "next", "coming"];
+
+// The forecast every provider turns its data into, so the responses don't depend on where it came from.
+// Everything is metric: degrees Celsius, kilometers per hour, and millimeters.
+
+// the weather right now
+struct Conditions {
+    desc : String,
+    temp : f64,
+    feels : f64,
+    humidity : f64, // percent
+    wind : f64,
+    wind_dir : String, // a compass point like "NNW"
+    precip : f64,
+}
+
+// the forecast for part of a day
+struct Hour {
+    hour : u32, // 0 to 23
+    desc : String,
+    temp : f64,
+    humidity : f64,
+    wind : f64,
+    precip : f64,
+    chance_rain : u32, // percent
+    chance_snow : u32,
+}
+
+// the forecast for one day
+struct Day {
+    weekday : usize, // 0 is Sunday
+    high : f64,
+    low : f64,
+    hours : Vec<Hour>,
+}
+
+struct Forecast {
+    current : Conditions,
+    days : Vec<Day>, // starting with today
+}
+
+// somewhere weather data comes from
+trait WeatherProvider : Send {
+    // gets the forecast for a place, or something to say about why it couldn't
+    fn forecast(&self, place : &str) -> Result<Forecast, String>;
+    fn uses_internet(&self) -> bool;
+}
+
+// which part of the day was asked about
+#[derive(Clone, Copy)]
+enum Period {
+    AllDay,
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+impl Period {
+    fn contains(&self, hour : u32) -> bool {
+        match self {
+            Period::AllDay => {return true},
+            Period::Morning => {return (6..12).contains(&hour)},
+            Period::Afternoon => {return (12..18).contains(&hour)},
+            Period::Evening => {return (18..21).contains(&hour)},
+            Period::Night => {return hour >= 21},
+        }
+    }
+    fn words(&self) -> &'static str {
+        match self {
+            Period::AllDay => {return ""},
+            Period::Morning => {return " morning"},
+            Period::Afternoon => {return " afternoon"},
+            Period::Evening => {return " evening"},
+            Period::Night => {return " night"},
+        }
+    }
+}
+
+// when the weather was asked about
+enum When {
+    Now,
+    Days(Vec<usize>, Period), // days from today
+}
+
+// what about the weather was asked
+#[derive(PartialEq)]
+enum Ask {
+    Conditions,
+    Rain,
+    Snow,
+    HighLow,
+}
 
 pub struct WeatherCommand {
-    default_loc : String,    
+    home : String,
+    places : HashMap<String, String>, // names like "work" for locations
+    units : Units,
+    details : Vec<String>,
+    provider : Box<dyn WeatherProvider>,
+    storage : Storage,
 }
 impl WeatherCommand {
-    pub fn new(default_loc : String) -> WeatherCommand {
-        return WeatherCommand{default_loc};
-    }
-    // for sending HTTP requests
-    fn request(url : String) -> Result<String, &'static str> {
-        let response = reqwest::blocking::get(url).or(Err("I was unable to connect to the weather service."))?;
-        if response.status().is_success() {
-            let text = response.text().or(Err("I had a problem understanding the weather service."))?;
-            return Ok(text);
+    pub fn new(config : &WeatherConfig, http : HttpService, storage : Storage) -> WeatherCommand {
+        let provider : Box<dyn WeatherProvider> = match config.provider.as_str() {
+            "open-meteo" => {Box::new(OpenMeteoProvider::new(http))},
+            "local" => {Box::new(LocalProvider::new(config.local_file.clone()))},
+            _ => {Box::new(WttrProvider::new(http))},
+        };
+        let mut home = config.home.clone();
+        let mut places = config.places.clone();
+        // locations set by voice replace the ones from the config
+        if let Some(saved) = storage.load(PLACES_FILE) {
+            if let Some(h) = saved["home"].as_str() {
+                home = String::from(h);
+            }
+            for (name, place) in saved["places"].entries() {
+                if let Some(place) = place.as_str() {
+                    places.insert(String::from(name), String::from(place));
+                }
+            }
+        }
+        return WeatherCommand{
+            home,
+            places,
+            units : config.units,
+            details : config.details.clone(),
+            provider,
+            storage,
+        };
+    }
+
+    // picks out requests like "set my work location to New York", giving back the name and the location
+    fn parse_setting(text : &str) -> Option<(String, String)> {
+        let start = text.find("set ")?;
+        let rest = &text[start + 4..];
+        let (name, place) = rest.split_once(" location to ").or_else(|| rest.split_once(" location as "))?;
+        let name = name.trim().trim_start_matches("my ").trim_start_matches("the ").trim();
+        let name = if name.is_empty() || name == "my" {"home"} else {name};
+        let place = place.trim();
+        if place.is_empty() {
+            return None;
+        }
+        return Some((String::from(name), String::from(place)));
+    }
+
+    // remembers a location by name, saving it for next time
+    fn set_place(&mut self, name : String, place : String) -> String {
+        if name == "home" {
+            self.home = place.clone();
         } else {
-            return Err("I didn't get a response from the weather service.");
+            self.places.insert(name.clone(), place.clone());
+        }
+        let mut saved_places = JsonValue::new_object();
+        for (n, p) in &self.places {
+            saved_places[n.as_str()] = p.clone().into();
+        }
+        let saved = json::object!{
+            home : self.home.clone(),
+            places : saved_places,
+        };
+        if let Err(e) = self.storage.save(PLACES_FILE, &saved) {
+            eprintln!("Error saving weather locations: {}", e);
+            return String::from("I couldn't save that location.");
+        }
+        return format!("Okay, your {} location is now {}.", name, place);
+    }
+
+    // the units asked for, or the usual ones if none were, along with the text without the request for units
+    fn parse_units(&self, text : &str) -> (Units, String) {
+        for (phrase, units) in UNIT_PHRASES {
+            if text.contains(phrase) {
+                return (units, text.replacen(phrase, "", 1));
+            }
+        }
+        return (self.units, String::from(text));
+    }
+
+    // the extra details to mention, either because they're always on or because they were asked about
+    fn parse_details(&self, text : &str) -> Vec<String> {
+        let mut details = self.details.clone();
+        let asked = [("humid", "humidity"), ("wind", "wind"), ("precipitation", "precipitation")];
+        for (word, detail) in asked {
+            if text.contains(word) && !details.iter().any(|d| d == detail) {
+                details.push(String::from(detail));
+            }
+        }
+        return details;
+    }
+
+    // works out when the user is asking about, given the weekday of the first forecast day (0 is Sunday)
+    fn parse_when(text : &str, first_weekday : usize) -> When {
+        let period = if text.contains("morning") {
+            Period::Morning
+        } else if text.contains("afternoon") {
+            Period::Afternoon
+        } else if text.contains("evening") {
+            Period::Evening
+        } else if text.contains("tonight") || text.contains("night") {
+            Period::Night
+        } else {
+            Period::AllDay
+        };
+        if text.contains("day after tomorrow") {
+            return When::Days(vec![2], period);
+        }
+        if text.contains("tomorrow") {
+            return When::Days(vec![1], period);
+        }
+        if text.contains("weekend") {
+            let days = (0..7).filter(|d| {
+                let weekday = (first_weekday + d) % 7;
+                weekday == 6 || weekday == 0
+            }).take(2).collect();
+            return When::Days(days, period);
+        }
+        for (name, weekday) in WEEKDAYS {
+            if text.contains(name) {
+                let weekday = weekday.num_days_from_sunday() as usize;
+                return When::Days(vec![(weekday + 7 - first_weekday) % 7], period);
+            }
+        }
+        if text.contains("today") || text.contains("forecast") || text.contains("later") {
+            return When::Days(vec![0], period);
+        }
+        match period {
+            Period::AllDay => {return When::Now},
+            _ => {return When::Days(vec![0], period)},
+        }
+    }
+
+    fn parse_ask(text : &str) -> Ask {
+        let words : Vec<&str> = text.split_whitespace().collect();
+        if words.contains(&"rain") || words.contains(&"raining") {
+            return Ask::Rain;
+        }
+        if words.contains(&"snow") || words.contains(&"snowing") {
+            return Ask::Snow;
+        }
+        if words.contains(&"high") || words.contains(&"low") {
+            return Ask::HighLow;
+        }
+        return Ask::Conditions;
+    }
+
+    // pulls the location out of the text, which comes after a word like "in" or "at"
+    // if more than one could be the location, like in "in the morning in Boston", the last one wins
+    fn parse_place(&self, text : &str) -> String {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let mut found : Option<Vec<&str>> = None;
+        for (i, word) in words.iter().enumerate() {
+            if !PLACE_WORDS.contains(word) {
+                continue;
+            }
+            let mut place : Vec<&str> = words[i + 1..].iter()
+                .take_while(|w| !PLACE_WORDS.contains(w) && !END_WORDS.contains(w)
+                    && !TIME_WORDS.contains(w) && !WEEKDAYS.iter().any(|(name, _)| name == *w))
+                .copied()
+                .collect();
+            while place.last().is_some_and(|w| FILLER_WORDS.contains(w)) {
+                place.pop();
+            }
+            if place.first() == Some(&"my") {
+                place.remove(0);
+            }
+            if !place.is_empty() {
+                found = Some(place);
+            }
+        }
+        let place = match found {
+            Some(p) => {p.join(" ")},
+            None => {return self.home.clone()},
+        };
+        if place == "home" {
+            return self.home.clone();
+        }
+        return self.places.get(&place).cloned().unwrap_or(place);
+    }
+
+    // the current conditions, plus whatever details were asked for
+    fn describe_now(current : &Conditions, place : &str, units : Units, details : &Vec<String>) -> String {
+        let mut answer = format!("The weather in {} is {}. It is {} degrees and feels like {} degrees.",
+            place, current.desc, spoken_temp(current.temp, units), spoken_temp(current.feels, units));
+        for detail in details {
+            let sentence = match detail.as_str() {
+                "humidity" => {format!(" The humidity is {} percent.", spoken_number(current.humidity, 0))},
+                "wind" => {format!(" The wind is {} from the {}.", spoken_speed(current.wind, units), compass_words(&current.wind_dir))},
+                "precipitation" => {format!(" There has been {} of precipitation.", spoken_length(current.precip, units))},
+                _ => {String::new()},
+            };
+            answer.push_str(&sentence);
+        }
+        return answer;
+    }
+
+    // one sentence about one day of the forecast
+    fn describe_day(day : &Day, offset : usize, period : Period, ask : &Ask, place : &str, units : Units, details : &Vec<String>) -> String {
+        let when = day_name(day, offset, period);
+        let hours : Vec<&Hour> = day.hours.iter().filter(|h| period.contains(h.hour)).collect();
+        match ask {
+            Ask::Rain | Ask::Snow => {
+                let what = if *ask == Ask::Rain {"rain"} else {"snow"};
+                let chance = hours.iter().map(|h| if *ask == Ask::Rain {h.chance_rain} else {h.chance_snow}).max().unwrap_or(0);
+                if chance == 0 {
+                    return format!("It doesn't look like it will {} {} in {}.", what, when, place);
+                }
+                return format!("There's a {} percent chance of {} {} in {}.", chance, what, when, place);
+            },
+            Ask::HighLow => {
+                return format!("The high {} in {} is {} degrees and the low is {} degrees.",
+                    when, place, spoken_temp(day.high, units), spoken_temp(day.low, units));
+            },
+            Ask::Conditions => {
+                // the description from the middle of the period is the most representative one
+                let desc = hours.get(hours.len() / 2).map(|h| h.desc.to_lowercase()).unwrap_or_default();
+                let mut answer = if let Period::AllDay = period {
+                    format!("{} in {} it will be {}, with a high of {} and a low of {} degrees.",
+                        capitalize(&when), place, desc, spoken_temp(day.high, units), spoken_temp(day.low, units))
+                } else {
+                    format!("{} in {} it will be {} and around {} degrees.",
+                        capitalize(&when), place, desc, spoken_temp(average_of(&hours, |h| h.temp), units))
+                };
+                for detail in details {
+                    let sentence = match detail.as_str() {
+                        "humidity" => {format!(" The humidity will be around {} percent.",
+                            spoken_number(average_of(&hours, |h| h.humidity), 0))},
+                        "wind" => {format!(" The wind will be around {}.",
+                            spoken_speed(average_of(&hours, |h| h.wind), units))},
+                        "precipitation" => {format!(" There will be {} of precipitation.",
+                            spoken_length(hours.iter().map(|h| h.precip).sum(), units))},
+                        _ => {String::new()},
+                    };
+                    answer.push_str(&sentence);
+                }
+                return answer;
+            },
         }
     }
 }
@@ -27,44 +380,156 @@ impl Command for WeatherCommand {
         return String::from("Weather");
     }
     fn desc(&self) -> String {
-        return String::from("This command can give you weather information about a given location.");
+        return String::from("This command can give you weather information and forecasts for a given location.");
     }
     fn help(&self) -> String {
-        return String::from("Mention the weather and a location to recieve weather data about that location. Make sure to precede the location with the word \"in\".");
+        return String::from("Mention the weather and a location to recieve weather data about that location. Precede the location with a word like \"in\", \"for\", or \"at\", or leave it out to use your home location. Say \"set my home location to\" a place to change it, or something like \"set my work location to\" a place to name it, then ask about the weather \"at work\". You can also ask about tomorrow, the weekend, a day of the week, or a part of the day, and whether it will rain or snow. Say \"in Celsius\" or \"in Fahrenheit\" to pick the units, and ask about humidity, wind, or precipitation for more detail.");
     }
     fn uses_internet(&self) -> bool {
-        return true;
+        return self.provider.uses_internet();
+    }
+    fn aliases(&self) -> Vec<String> {
+        return vec![String::from("forecast"), String::from("temperature")];
+    }
+    fn examples(&self) -> Vec<String> {
+        return vec![String::from("what's the weather in Boston"), String::from("will it rain this afternoon"),
+            String::from("what's the weather tomorrow"), String::from("what are the high and low today")];
     }
     fn recognize(&self, text : String) -> bool {
-        return text.contains("weather");
+        let words : Vec<&str> = text.split_whitespace().collect();
+        return text.contains("weather") || text.contains("forecast") || text.contains("humidity") || text.contains("how windy")
+            || (words.contains(&"will") && (words.contains(&"rain") || words.contains(&"snow")))
+            || (words.contains(&"high") && words.contains(&"low"))
+            || WeatherCommand::parse_setting(&text).is_some();
     }
     fn effect(&mut self, text : String, speak : Sender<SpeakMessage>) -> CommandResult {
-        let mut place = self.default_loc.clone();
-        let words = text.split_whitespace().collect::<Vec<_>>();
-        if words.contains(&"in") {
-            let mut iter = words.split_inclusive(|s| *s == "in");
-            place = iter.nth(1).unwrap().join("+");
+        if let Some((name, place)) = WeatherCommand::parse_setting(&text) {
+            speak.send(SpeakMessage::Say(self.set_place(name, place))).unwrap();
+            return CommandResult::Done;
         }
-        let response = WeatherCommand::request(format!("https://wttr.in/{}?format=j1", place));
-        let stringy : String = match response {
-            Ok(s) => {s},
+        let (units, text) = self.parse_units(&text);
+        let details = self.parse_details(&text);
+        let place = self.parse_place(&text);
+        let forecast = match self.provider.forecast(&place) {
+            Ok(f) => {f},
             Err(e) => {
                 speak.send(SpeakMessage::Say(format!("{} Please try again later.", e))).unwrap();
                 return CommandResult::Done;
             }
         };
-        let parsed = json::parse(&stringy).expect("Couldn't parse JSON response");
-        println!("{:?}", parsed["current_condition"].pretty(2));
-        let weather = parsed["current_condition"][0]["weatherDesc"][0]["value"].dump();
-        let temp = parsed["current_condition"][0]["temp_F"].dump();
-        let feels = parsed["current_condition"][0]["FeelsLikeF"].dump();
-        speak.send(SpeakMessage::Say(format!("The weather in {} is {}. It is {} degrees and feels like {} degrees.",
-                                             place.replace("+"," "),
-                                             weather.trim_matches('"'),
-                                             temp.trim_matches('"'),
-                                             feels.trim_matches('"')))).unwrap();
+        let first_weekday = forecast.days.first().map(|d| d.weekday).unwrap_or(0);
+        let ask = WeatherCommand::parse_ask(&text);
+        let when = match WeatherCommand::parse_when(&text, first_weekday) {
+            // only the current conditions make sense for "now", anything else looks at today's forecast
+            When::Now if ask != Ask::Conditions => {When::Days(vec![0], Period::AllDay)},
+            w => {w},
+        };
+        let answer = match when {
+            When::Now => {WeatherCommand::describe_now(&forecast.current, &place, units, &details)},
+            When::Days(days, period) => {
+                let forecast_days = forecast.days.len();
+                if days.is_empty() || days.iter().any(|d| *d >= forecast_days) {
+                    format!("I only have the forecast for the next {} days.", forecast_days)
+                } else {
+                    days.iter()
+                        .map(|d| WeatherCommand::describe_day(&forecast.days[*d], *d, period, &ask, &place, units, &details))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+            },
+        };
+        speak.send(SpeakMessage::Say(answer)).unwrap();
         return CommandResult::Done;
     }
 }
 
+// gets a number out of JSON whether it was given as a number or a string, like wttr.in does
+fn number_of(value : &JsonValue) -> f64 {
+    return value.as_f64().or_else(|| value.as_str().and_then(|s| s.trim().parse().ok())).unwrap_or(0.0);
+}
+
+// the average of something across the hours of a forecast
+fn average_of(hours : &Vec<&Hour>, value : impl Fn(&Hour) -> f64) -> f64 {
+    if hours.is_empty() {
+        return 0.0;
+    }
+    return hours.iter().map(|h| value(h)).sum::<f64>() / hours.len() as f64;
+}
+
+// formats a number to be read aloud, rounded to some number of decimal places
+fn spoken_number(value : f64, decimals : usize) -> String {
+    let rounded = format!("{:.*}", decimals, value.abs());
+    // "-0" and "0.0" both just mean nothing
+    if rounded.parse::<f64>().unwrap_or(0.0) == 0.0 {
+        return String::from("0");
+    }
+    if value < 0.0 {
+        return format!("minus {}", rounded);
+    }
+    return rounded;
+}
 
+// a temperature in Celsius, converted to the units asked for
+fn spoken_temp(celsius : f64, units : Units) -> String {
+    match units {
+        Units::Metric => {return spoken_number(celsius, 0)},
+        Units::Imperial => {return spoken_number(celsius * 9.0 / 5.0 + 32.0, 0)},
+    }
+}
+
+// a speed in kilometers per hour, converted to the units asked for
+fn spoken_speed(kmph : f64, units : Units) -> String {
+    match units {
+        Units::Metric => {return format!("{} kilometers per hour", spoken_number(kmph, 0))},
+        Units::Imperial => {return format!("{} miles per hour", spoken_number(kmph * 0.621371, 0))},
+    }
+}
+
+// an amount of precipitation in millimeters, converted to the units asked for
+fn spoken_length(mm : f64, units : Units) -> String {
+    match units {
+        Units::Metric => {return format!("{} millimeters", spoken_number(mm, 1))},
+        Units::Imperial => {return format!("{} inches", spoken_number(mm / 25.4, 1))},
+    }
+}
+
+// turns a compass point like "NNW" into words
+fn compass_words(point : &str) -> String {
+    let words : Vec<&str> = point.chars().filter_map(|c| match c {
+        'N' => {Some("north")},
+        'S' => {Some("south")},
+        'E' => {Some("east")},
+        'W' => {Some("west")},
+        _ => {None},
+    }).collect();
+    return words.join(" ");
+}
+
+// how to say which day is being talked about, like "tomorrow afternoon" or "on Saturday"
+fn day_name(day : &Day, offset : usize, period : Period) -> String {
+    match (offset, period) {
+        (0, Period::AllDay) => {return String::from("today")},
+        (0, Period::Night) => {return String::from("tonight")},
+        (0, _) => {return format!("this{}", period.words())},
+        (1, _) => {return format!("tomorrow{}", period.words())},
+        _ => {
+            let name = WEEKDAYS.iter().find(|(_, w)| w.num_days_from_sunday() as usize == day.weekday % 7).map(|(n, _)| *n).unwrap_or("");
+            return format!("on {}{}", capitalize(name), period.words());
+        },
+    }
+}
+
+// the day of the week for a date like 2024-05-17, with 0 as Sunday
+fn weekday(date : &str) -> Option<usize> {
+    let parts : Vec<u32> = date.split('-').filter_map(|p| p.parse().ok()).collect();
+    if parts.len() != 3 || parts[0] == 0 || parts[1] < 1 || parts[1] > 12 {
+        return None;
+    }
+    // Sakamoto's method
+    let offsets = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let (mut y, m, d) = (parts[0], parts[1], parts[2]);
+    if m < 3 {
+        y -= 1;
+    }
+    return Some(((y + y / 4 - y / 100 + y / 400 + offsets[(m - 1) as usize] + d) % 7) as usize);
+}
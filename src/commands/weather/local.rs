@@ -0,0 +1,64 @@
+use crate::commands::weather::{WeatherProvider, Forecast, Conditions, Day, Hour, number_of, weekday};
+use std::fs;
+use json;
+use json::JsonValue;
+
+// reads the forecast from a JSON file already in ZINNIA's own format (see resources/weather_example.json),
+// for using weather data from somewhere else on the computer without needing the internet
+pub struct LocalProvider {
+    path : String, // "{place}" in here gets replaced with the place asked about
+}
+impl LocalProvider {
+    pub fn new(path : String) -> LocalProvider {
+        return LocalProvider{path};
+    }
+}
+impl WeatherProvider for LocalProvider {
+    fn forecast(&self, place : &str) -> Result<Forecast, String> {
+        let path = self.path.replace("{place}", &place.to_lowercase().replace(' ', "_"));
+        let contents = fs::read_to_string(&path).or(Err(format!("I couldn't find any weather for {}.", place)))?;
+        let parsed = json::parse(&contents).or(Err(String::from("I had a problem understanding the weather file.")))?;
+        let current = &parsed["current"];
+        let mut days = Vec::new();
+        for day in parsed["days"].members() {
+            let mut hours = Vec::new();
+            for hour in day["hours"].members() {
+                hours.push(Hour {
+                    hour : number_of(&hour["hour"]) as u32,
+                    desc : text_of(&hour["description"]),
+                    temp : number_of(&hour["temperature"]),
+                    humidity : number_of(&hour["humidity"]),
+                    wind : number_of(&hour["wind_speed"]),
+                    precip : number_of(&hour["precipitation"]),
+                    chance_rain : number_of(&hour["chance_of_rain"]) as u32,
+                    chance_snow : number_of(&hour["chance_of_snow"]) as u32,
+                });
+            }
+            days.push(Day {
+                weekday : weekday(day["date"].as_str().unwrap_or("")).unwrap_or(0),
+                high : number_of(&day["high"]),
+                low : number_of(&day["low"]),
+                hours,
+            });
+        }
+        return Ok(Forecast {
+            current : Conditions {
+                desc : text_of(&current["description"]),
+                temp : number_of(&current["temperature"]),
+                feels : number_of(&current["feels_like"]),
+                humidity : number_of(&current["humidity"]),
+                wind : number_of(&current["wind_speed"]),
+                wind_dir : text_of(&current["wind_direction"]),
+                precip : number_of(&current["precipitation"]),
+            },
+            days,
+        });
+    }
+    fn uses_internet(&self) -> bool {
+        return false;
+    }
+}
+
+fn text_of(value : &JsonValue) -> String {
+    return String::from(value.as_str().unwrap_or(""));
+}
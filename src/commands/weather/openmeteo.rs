@@ -0,0 +1,123 @@
+use crate::commands::weather::{WeatherProvider, Forecast, Conditions, Day, Hour, number_of, weekday, FORECAST_MAX_AGE, PLACE_MAX_AGE};
+use crate::http::{HttpService, HttpError};
+use reqwest::Url;
+use json;
+
+// which of the hourly values to ask Open-Meteo for
+const HOURLY : &str = "temperature_2m,relative_humidity_2m,precipitation_probability,precipitation,snowfall,weather_code,wind_speed_10m";
+const CURRENT : &str = "temperature_2m,apparent_temperature,relative_humidity_2m,precipitation,weather_code,wind_speed_10m,wind_direction_10m";
+const COMPASS : [&str; 16] = ["N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW"];
+
+// gets weather from Open-Meteo, looking up where the place is with its geocoding service first
+pub struct OpenMeteoProvider {
+    http : HttpService,
+}
+impl OpenMeteoProvider {
+    pub fn new(http : HttpService) -> OpenMeteoProvider {
+        return OpenMeteoProvider{http};
+    }
+
+    // finds the latitude and longitude of a place
+    fn locate(&self, place : &str) -> Result<(f64, f64), String> {
+        let base = self.http.base_url("open-meteo-geocoding", "https://geocoding-api.open-meteo.com");
+        // parse_with_params escapes the place, since names can have things like "&" or accents in them
+        let url = Url::parse_with_params(&format!("{}/v1/search", base), &[("count", "1"), ("name", place)])
+            .map_err(|_| HttpError::Connect.describe("location service"))?;
+        let body = self.http.get_cached(url.as_str(), "application/json", PLACE_MAX_AGE).map_err(|e| e.describe("location service"))?;
+        let parsed = json::parse(&body).or(Err(String::from("I had a problem understanding the location service.")))?;
+        let result = &parsed["results"][0];
+        if result.is_null() {
+            return Err(format!("I couldn't find a place called {}.", place));
+        }
+        return Ok((number_of(&result["latitude"]), number_of(&result["longitude"])));
+    }
+}
+impl WeatherProvider for OpenMeteoProvider {
+    fn forecast(&self, place : &str) -> Result<Forecast, String> {
+        let (latitude, longitude) = self.locate(place)?;
+        let url = format!("{}/v1/forecast?latitude={}&longitude={}&current={}&hourly={}&daily=temperature_2m_max,temperature_2m_min&timezone=auto&forecast_days=3",
+            self.http.base_url("open-meteo", "https://api.open-meteo.com"), latitude, longitude, CURRENT, HOURLY);
+        let body = self.http.get_cached(&url, "application/json", FORECAST_MAX_AGE).map_err(|e| e.describe("weather service"))?;
+        let parsed = json::parse(&body).or(Err(String::from("I had a problem understanding the weather service.")))?;
+        let current = &parsed["current"];
+        if current.is_null() {
+            return Err(String::from("The weather service didn't send any weather."));
+        }
+        let daily = &parsed["daily"];
+        let hourly = &parsed["hourly"];
+        let mut days = Vec::new();
+        for (i, date) in daily["time"].members().enumerate() {
+            let date = date.as_str().unwrap_or("");
+            let mut hours = Vec::new();
+            // hourly times look like "2024-05-17T15:00", so the ones for this day start with its date
+            for (j, time) in hourly["time"].members().enumerate() {
+                let time = time.as_str().unwrap_or("");
+                if !time.starts_with(date) {
+                    continue;
+                }
+                let chance = number_of(&hourly["precipitation_probability"][j]) as u32;
+                let snowing = number_of(&hourly["snowfall"][j]) > 0.0;
+                hours.push(Hour {
+                    hour : time.get(11..13).and_then(|h| h.parse().ok()).unwrap_or(0),
+                    desc : String::from(describe_code(number_of(&hourly["weather_code"][j]) as u32)),
+                    temp : number_of(&hourly["temperature_2m"][j]),
+                    humidity : number_of(&hourly["relative_humidity_2m"][j]),
+                    wind : number_of(&hourly["wind_speed_10m"][j]),
+                    precip : number_of(&hourly["precipitation"][j]),
+                    // Open-Meteo only gives one chance of precipitation, so it's split by whether snow is expected
+                    chance_rain : if snowing {0} else {chance},
+                    chance_snow : if snowing {chance} else {0},
+                });
+            }
+            days.push(Day {
+                weekday : weekday(date).unwrap_or(0),
+                high : number_of(&daily["temperature_2m_max"][i]),
+                low : number_of(&daily["temperature_2m_min"][i]),
+                hours,
+            });
+        }
+        let direction = number_of(&current["wind_direction_10m"]);
+        return Ok(Forecast {
+            current : Conditions {
+                desc : String::from(describe_code(number_of(&current["weather_code"]) as u32)),
+                temp : number_of(&current["temperature_2m"]),
+                feels : number_of(&current["apparent_temperature"]),
+                humidity : number_of(&current["relative_humidity_2m"]),
+                wind : number_of(&current["wind_speed_10m"]),
+                wind_dir : String::from(COMPASS[((direction / 22.5).round() as usize) % 16]),
+                precip : number_of(&current["precipitation"]),
+            },
+            days,
+        });
+    }
+    fn uses_internet(&self) -> bool {
+        return true;
+    }
+}
+
+// turns a WMO weather code into words
+fn describe_code(code : u32) -> &'static str {
+    match code {
+        0 => {return "Clear"},
+        1 => {return "Mainly clear"},
+        2 => {return "Partly cloudy"},
+        3 => {return "Overcast"},
+        45 | 48 => {return "Foggy"},
+        51 | 53 | 55 => {return "Drizzle"},
+        56 | 57 => {return "Freezing drizzle"},
+        61 => {return "Light rain"},
+        63 => {return "Rain"},
+        65 => {return "Heavy rain"},
+        66 | 67 => {return "Freezing rain"},
+        71 => {return "Light snow"},
+        73 => {return "Snow"},
+        75 => {return "Heavy snow"},
+        77 => {return "Snow grains"},
+        80 | 81 => {return "Rain showers"},
+        82 => {return "Violent rain showers"},
+        85 | 86 => {return "Snow showers"},
+        95 => {return "Thunderstorms"},
+        96 | 99 => {return "Thunderstorms with hail"},
+        _ => {return "Unknown conditions"},
+    }
+}
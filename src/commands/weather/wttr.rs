@@ -0,0 +1,71 @@
+use crate::commands::weather::{WeatherProvider, Forecast, Conditions, Day, Hour, number_of, weekday, FORECAST_MAX_AGE};
+use crate::http::{HttpService, HttpError};
+use reqwest::Url;
+use json;
+use json::JsonValue;
+
+// gets weather from wttr.in's JSON format
+pub struct WttrProvider {
+    http : HttpService,
+}
+impl WttrProvider {
+    pub fn new(http : HttpService) -> WttrProvider {
+        return WttrProvider{http};
+    }
+}
+impl WeatherProvider for WttrProvider {
+    fn forecast(&self, place : &str) -> Result<Forecast, String> {
+        let mut url = Url::parse(&self.http.base_url("weather", "https://wttr.in")).map_err(|_| HttpError::Connect.describe("weather service"))?;
+        // the place is part of the path, so things like "/" and "?" in it have to be escaped
+        url.path_segments_mut().map_err(|_| HttpError::Connect.describe("weather service"))?.pop_if_empty().push(place);
+        url.set_query(Some("format=j1"));
+        let body = self.http.get_cached(url.as_str(), "application/json", FORECAST_MAX_AGE).map_err(|e| e.describe("weather service"))?;
+        let parsed = json::parse(&body).or(Err(String::from("I had a problem understanding the weather service.")))?;
+        let current = &parsed["current_condition"][0];
+        if current.is_null() {
+            return Err(String::from("The weather service didn't send any weather."));
+        }
+        let mut days = Vec::new();
+        for day in parsed["weather"].members() {
+            let mut hours = Vec::new();
+            for hour in day["hourly"].members() {
+                hours.push(Hour {
+                    // wttr.in gives times like "1500"
+                    hour : number_of(&hour["time"]) as u32 / 100,
+                    desc : description(hour),
+                    temp : number_of(&hour["tempC"]),
+                    humidity : number_of(&hour["humidity"]),
+                    wind : number_of(&hour["windspeedKmph"]),
+                    precip : number_of(&hour["precipMM"]),
+                    chance_rain : number_of(&hour["chanceofrain"]) as u32,
+                    chance_snow : number_of(&hour["chanceofsnow"]) as u32,
+                });
+            }
+            days.push(Day {
+                weekday : weekday(day["date"].as_str().unwrap_or("")).unwrap_or(0),
+                high : number_of(&day["maxtempC"]),
+                low : number_of(&day["mintempC"]),
+                hours,
+            });
+        }
+        return Ok(Forecast {
+            current : Conditions {
+                desc : description(current),
+                temp : number_of(&current["temp_C"]),
+                feels : number_of(&current["FeelsLikeC"]),
+                humidity : number_of(&current["humidity"]),
+                wind : number_of(&current["windspeedKmph"]),
+                wind_dir : String::from(current["winddir16Point"].as_str().unwrap_or("")),
+                precip : number_of(&current["precipMM"]),
+            },
+            days,
+        });
+    }
+    fn uses_internet(&self) -> bool {
+        return true;
+    }
+}
+
+fn description(value : &JsonValue) -> String {
+    return String::from(value["weatherDesc"][0]["value"].as_str().unwrap_or("").trim());
+}
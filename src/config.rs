@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+use toml;
+
+// where the settings file is read from
+pub const CONFIG_FILE : &str = "zinnia.toml";
+
+// settings read from the config file at startup, anything missing gets its default
+pub struct Config {
+    pub offline : bool, // start in offline mode
+    pub data_dir : String, // where anything remembered between runs is kept
+    pub http : HttpConfig,
+    pub weather : WeatherConfig,
+}
+
+// settings for the shared HTTP client, from the [http] table
+pub struct HttpConfig {
+    pub timeout : Duration,
+    pub proxy : Option<String>,
+    pub user_agent : String,
+    pub base_urls : HashMap<String, String>, // service name to the URL to use instead of the usual one
+}
+
+// which units to give measurements in
+#[derive(Clone, Copy, PartialEq)]
+pub enum Units {
+    Imperial,
+    Metric,
+}
+
+// settings for the weather command, from the [weather] table
+pub struct WeatherConfig {
+    pub units : Units,
+    pub details : Vec<String>, // extra things to always mention, out of "humidity", "wind", and "precipitation"
+    pub provider : String, // where the weather comes from, "wttr", "open-meteo", or "local"
+    pub local_file : String, // the file the "local" provider reads from
+    pub home : String, // the location used when none is given
+    pub places : HashMap<String, String>, // names like "work" for locations, usable as "the weather at work"
+}
+
+impl Config {
+    pub fn default() -> Config {
+        return Config {
+            offline : false,
+            data_dir : String::from("data"),
+            http : HttpConfig {
+                timeout : Duration::from_secs(10),
+                proxy : None,
+                user_agent : format!("zinnia/{}", env!("CARGO_PKG_VERSION")),
+                base_urls : HashMap::new(),
+            },
+            weather : WeatherConfig {
+                units : Units::Imperial,
+                details : Vec::new(),
+                provider : String::from("wttr"),
+                local_file : String::from("weather.json"),
+                home : String::from("Drums"),
+                places : HashMap::new(),
+            },
+        };
+    }
+
+    // reads the config file, falling back to the defaults if it's missing or broken
+    pub fn load(path : &str) -> Config {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => {c},
+            Err(_) => {return Config::default()}, // no file just means default settings
+        };
+        match Config::parse(&contents) {
+            Ok(c) => {return c},
+            Err(e) => {
+                eprintln!("Error reading {}, using default settings: {}", path, e);
+                return Config::default();
+            }
+        }
+    }
+
+    fn parse(contents : &str) -> Result<Config, String> {
+        let table = contents.parse::<toml::Table>().map_err(|e| e.to_string())?;
+        let mut config = Config::default();
+        if let Some(v) = table.get("offline") {
+            config.offline = v.as_bool().ok_or("\"offline\" must be true or false")?;
+        }
+        if let Some(v) = table.get("data_dir") {
+            config.data_dir = String::from(v.as_str().ok_or("\"data_dir\" must be a string")?);
+        }
+        if let Some(v) = table.get("http") {
+            let http = v.as_table().ok_or("\"http\" must be a table")?;
+            if let Some(v) = http.get("timeout") {
+                let secs = v.as_integer().filter(|t| *t > 0).ok_or("\"http.timeout\" must be a number of seconds")?;
+                config.http.timeout = Duration::from_secs(secs as u64);
+            }
+            if let Some(v) = http.get("proxy") {
+                config.http.proxy = Some(String::from(v.as_str().ok_or("\"http.proxy\" must be a string")?));
+            }
+            if let Some(v) = http.get("user_agent") {
+                config.http.user_agent = String::from(v.as_str().ok_or("\"http.user_agent\" must be a string")?);
+            }
+            if let Some(v) = http.get("base_urls") {
+                let urls = v.as_table().ok_or("\"http.base_urls\" must be a table")?;
+                for (service, url) in urls {
+                    let url = url.as_str().ok_or("\"http.base_urls\" must only contain strings")?;
+                    config.http.base_urls.insert(service.clone(), String::from(url));
+                }
+            }
+        }
+        if let Some(v) = table.get("weather") {
+            let weather = v.as_table().ok_or("\"weather\" must be a table")?;
+            if let Some(v) = weather.get("units") {
+                config.weather.units = match v.as_str() {
+                    Some("imperial") => {Units::Imperial},
+                    Some("metric") => {Units::Metric},
+                    _ => {return Err(String::from("\"weather.units\" must be \"imperial\" or \"metric\""))},
+                };
+            }
+            if let Some(v) = weather.get("provider") {
+                match v.as_str() {
+                    Some(p) if ["wttr", "open-meteo", "local"].contains(&p) => {config.weather.provider = String::from(p)},
+                    _ => {return Err(String::from("\"weather.provider\" must be \"wttr\", \"open-meteo\", or \"local\""))},
+                }
+            }
+            if let Some(v) = weather.get("local_file") {
+                config.weather.local_file = String::from(v.as_str().ok_or("\"weather.local_file\" must be a string")?);
+            }
+            if let Some(v) = weather.get("home") {
+                config.weather.home = String::from(v.as_str().ok_or("\"weather.home\" must be a string")?);
+            }
+            if let Some(v) = weather.get("places") {
+                let places = v.as_table().ok_or("\"weather.places\" must be a table")?;
+                for (name, place) in places {
+                    let place = place.as_str().ok_or("\"weather.places\" must only contain strings")?;
+                    config.weather.places.insert(name.to_lowercase(), String::from(place));
+                }
+            }
+            if let Some(v) = weather.get("details") {
+                let details = v.as_array().ok_or("\"weather.details\" must be a list")?;
+                for detail in details {
+                    match detail.as_str() {
+                        Some(d) if ["humidity", "wind", "precipitation"].contains(&d) => {
+                            config.weather.details.push(String::from(d));
+                        },
+                        _ => {return Err(String::from("\"weather.details\" can only have \"humidity\", \"wind\", and \"precipitation\""))},
+                    }
+                }
+            }
+        }
+        return Ok(config);
+    }
+}
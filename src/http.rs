@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use reqwest;
+use json;
+use crate::config::HttpConfig;
+use crate::storage::Storage;
+
+// where get_cached keeps responses, inside the data folder
+const CACHE_DIR : &str = "cache";
+
+// the ways a request can fail
+pub enum HttpError {
+    Offline,
+    Connect,
+    Status,
+    Body,
+}
+impl HttpError {
+    // turns the error into something to say, given what the service is called
+    pub fn describe(&self, service : &str) -> String {
+        match self {
+            HttpError::Offline => {return format!("I'm in offline mode, so I can't reach the {}.", service)},
+            HttpError::Connect => {return format!("I was unable to connect to the {}.", service)},
+            HttpError::Status => {return format!("I didn't get a response from the {}.", service)},
+            HttpError::Body => {return format!("I had a problem understanding the {}.", service)},
+        }
+    }
+}
+
+// one HTTP client shared by every command, so connections get reused and settings are in one place
+// cloning it is cheap, and all the clones share the same connection pool
+#[derive(Clone)]
+pub struct HttpService {
+    client : reqwest::blocking::Client,
+    base_urls : Arc<HashMap<String, String>>,
+    offline : Arc<AtomicBool>, // no requests are made while this is set
+    storage : Storage, // get_cached keeps responses in its cache folder
+}
+impl HttpService {
+    pub fn new(config : &HttpConfig, offline : Arc<AtomicBool>, storage : Storage) -> HttpService {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(config.timeout)
+            .user_agent(config.user_agent.clone());
+        if let Some(proxy) = &config.proxy {
+            match reqwest::Proxy::all(proxy) {
+                Ok(p) => {builder = builder.proxy(p)},
+                Err(e) => {eprintln!("Error with HTTP proxy {}, not using it: {}", proxy, e);}
+            }
+        }
+        let client = match builder.build() {
+            Ok(c) => {c},
+            Err(e) => {
+                eprintln!("Error setting up the HTTP client, using the default one: {}", e);
+                reqwest::blocking::Client::new()
+            }
+        };
+        let http = HttpService {
+            client,
+            base_urls : Arc::new(config.base_urls.clone()),
+            offline,
+            storage,
+        };
+        http.prune_cache();
+        return http;
+    }
+
+    // the base URL to use for a service, which the config can point somewhere else (like a local stand-in)
+    pub fn base_url(&self, service : &str, default : &str) -> String {
+        let url = self.base_urls.get(service).map(|u| u.as_str()).unwrap_or(default);
+        return String::from(url.trim_end_matches('/'));
+    }
+
+    // gets the body of a URL as text, using the client's default timeout
+    pub fn get(&self, url : &str, accept : &str) -> Result<String, HttpError> {
+        return self.send(self.client.get(url), accept);
+    }
+
+    // like get, but reuses a saved response if it's newer than max_age, and saves new responses
+    pub fn get_cached(&self, url : &str, accept : &str, max_age : Duration) -> Result<String, HttpError> {
+        let name = format!("{}/{:016x}.json", CACHE_DIR, fnv_hash(url));
+        let now = unix_time();
+        if let Some(saved) = self.storage.load(&name) {
+            // the URL is checked too, on the off chance two of them hash the same
+            let age = now.saturating_sub(saved["time"].as_u64().unwrap_or(0));
+            if saved["url"] == url && age <= max_age.as_secs() {
+                if let Some(body) = saved["body"].as_str() {
+                    return Ok(String::from(body));
+                }
+            }
+            // it's out of date, so it's gone now even if getting a new one fails
+            let _ = fs::remove_file(self.storage.path(&name));
+        }
+        let body = self.get(url, accept)?;
+        let saved = json::object!{
+            url : url,
+            time : now,
+            expires : now + max_age.as_secs(), // so prune_cache knows when to delete it
+            body : body.clone(),
+        };
+        if let Err(e) = self.storage.save(&name, &saved) {
+            eprintln!("Error saving response from {}: {}", url, e);
+        }
+        self.prune_cache();
+        return Ok(body);
+    }
+
+    // deletes every saved response that's out of date, so ones that are never asked for again don't pile up
+    // this happens at startup and whenever a new response is saved
+    fn prune_cache(&self) {
+        let entries = match fs::read_dir(self.storage.path(CACHE_DIR)) {
+            Ok(e) => {e},
+            Err(_) => {return}, // nothing's been saved yet
+        };
+        let now = unix_time();
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = format!("{}/{}", CACHE_DIR, entry.file_name().to_string_lossy());
+            // ones saved before responses knew when they expired count as expired
+            let expires = self.storage.load(&name).and_then(|saved| saved["expires"].as_u64()).unwrap_or(0);
+            if expires < now {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    // like get, but with a timeout just for this request
+    pub fn get_with_timeout(&self, url : &str, accept : &str, timeout : Duration) -> Result<String, HttpError> {
+        return self.send(self.client.get(url).timeout(timeout), accept);
+    }
+
+    fn send(&self, request : reqwest::blocking::RequestBuilder, accept : &str) -> Result<String, HttpError> {
+        if self.offline.load(Ordering::SeqCst) {
+            return Err(HttpError::Offline);
+        }
+        let response = request
+            .header(reqwest::header::ACCEPT, accept)
+            .send().or(Err(HttpError::Connect))?;
+        if !response.status().is_success() {
+            eprintln!("Request to {} failed with status {}", response.url(), response.status());
+            return Err(HttpError::Status);
+        }
+        return response.text().or(Err(HttpError::Body));
+    }
+}
+
+// seconds since 1970, which is how saved responses keep track of when they're from
+fn unix_time() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+}
+
+// a quick hash for turning URLs into file names, which (unlike the standard library's) won't change between versions
+fn fnv_hash(text : &str) -> u64 {
+    let mut hash : u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
@@ -3,6 +3,8 @@ use std::io;
 use std::io::{Write, Cursor};
 use std::process::Stdio;
 use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Sender, SyncSender, Receiver, TryRecvError};
 use std::thread;
 use std::collections::VecDeque;
@@ -17,20 +19,49 @@ use tray_item::{IconSource, TrayItem};
 
 use vosk::{Model, Recognizer, DecodingState};
 
-use notify_rust::{Notification, Timeout};
+use notify_rust::{Notification, NotificationHandle, Timeout};
 
 mod commands;
-use commands::{CommandDirector, DispatchResult};
+use commands::{CommandDirector, DispatchResult, PLUGIN_HOST_ARG, run_plugin_host};
+mod config;
+use config::Config;
+mod http;
+mod storage;
+mod timers;
+mod workers;
 
 
 // Messages to be sent to the speech thread
 enum SpeakMessage{
     Say(String),
+    Ack(String), // spoken like Say, but not remembered as the last response
+    Repeat, // says the last response again
+    Remind(String), // spoken like Say, with a notification that stays until it's dismissed
+    Earcon(Earcon), // a short sound, for when words would be too much
+}
+
+// short sounds that mark something happening
+enum Earcon {
+    Begin,
+    Switch,
+    Finish,
+}
+impl Earcon {
+    // the notes to play, as a frequency in hertz and a length in milliseconds
+    fn notes(&self) -> &'static [(f32, u32)] {
+        match self {
+            Earcon::Begin => {return &[(523.25, 120), (659.25, 120), (783.99, 200)]},
+            Earcon::Switch => {return &[(783.99, 150), (783.99, 150)]},
+            Earcon::Finish => {return &[(783.99, 120), (659.25, 120), (523.25, 300)]},
+        }
+    }
 }
 
 // Messages to be sent from the tray icon to the main program
 enum TrayMessage {
     Close,
+    ToggleOffline,
+    StopListening,
 }
 
 // State of the overall program
@@ -41,10 +72,20 @@ enum State {
 }
 
 fn main() {
+    // plugins run in their own copy of ZINNIA, so one that crashes can't take the rest down with it
+    let args : Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == PLUGIN_HOST_ARG {
+        run_plugin_host(&args[2]);
+        return;
+    }
+    let config = Config::load(config::CONFIG_FILE);
+    // whether commands that need the internet are allowed to run, shared with the command director
+    let offline = Arc::new(AtomicBool::new(config.offline));
+
     // make a channel for sending messages to be spoken to the talk thread
     let (speaktx, speakrx) = mpsc::channel::<SpeakMessage>();
     
-    let (in_stream, speechrx, statetx) = match transcription_init(String::from("Zinnia here!"),
+    let (in_stream, speechrx, partialrx, statetx) = match transcription_init(String::from("Zinnia here!"),
         String::from("./resources/Yo_Zinnia2.rpw"),
         String::from("resources/vosk-model-en-us-0.21"),
         speaktx.clone()) {
@@ -57,9 +98,35 @@ fn main() {
     
     // make a thread to handle talking, and give it the receiver end of the channel
     let talk_thread = thread::spawn(move || {
+        let mut last_said = String::new();
         for message in speakrx {
-            let SpeakMessage::Say(thing) = message;
-            send_notif(&thing);
+            let mut lasting = false;
+            let thing = match message {
+                SpeakMessage::Say(thing) => {
+                    last_said = thing.clone();
+                    thing
+                },
+                SpeakMessage::Remind(thing) => {
+                    last_said = thing.clone();
+                    lasting = true;
+                    thing
+                },
+                SpeakMessage::Ack(thing) => {thing},
+                SpeakMessage::Earcon(earcon) => {
+                    if let Err(e) = play_earcon(&earcon) {
+                        eprintln!("Error playing a sound: {}", e);
+                    }
+                    continue;
+                },
+                SpeakMessage::Repeat => {
+                    if last_said.is_empty() {
+                        String::from("I haven't said anything yet.")
+                    } else {
+                        last_said.clone()
+                    }
+                },
+            };
+            send_notif(&thing, lasting);
             match say(thing.to_string()) {
                 Ok(_) => {},
                 Err(e) => {eprintln!("Error with speech synthesis: {}", e);}
@@ -74,7 +141,10 @@ fn main() {
     let tray_rx = tray_menu_init();
 
     // set up the command director
-    let mut command_director = CommandDirector::new(speaktx.clone());
+    let mut command_director = CommandDirector::new(speaktx.clone(), &config, offline.clone());
+
+    // notification showing what's been heard so far, so misrecognitions can be seen as they happen
+    let mut partial_notif : Option<NotificationHandle> = None;
     
     // watch for tray messages and spoken input
     loop {
@@ -82,6 +152,19 @@ fn main() {
             Ok(TrayMessage::Close) => {
                 break; // breaking out of this loop will end the program
             }
+            Ok(TrayMessage::StopListening) => {
+                // ends any conversation, like a note being dictated, and goes back to waiting for the wake word
+                command_director.end_conversation();
+                if let Some(handle) = partial_notif.take() {
+                    handle.close();
+                }
+                statetx.send(State::Waiting).unwrap();
+            }
+            Ok(TrayMessage::ToggleOffline) => {
+                let now_offline = !offline.fetch_xor(true, Ordering::SeqCst);
+                let _ = speaktx.send(SpeakMessage::Say(String::from(
+                    if now_offline {"Offline mode is on."} else {"Offline mode is off."})));
+            }
             Err(e) => {
                 match e {
                     TryRecvError::Empty => {},
@@ -89,13 +172,29 @@ fn main() {
                 }
             }
         }
+        match partialrx.try_recv() {
+            Ok(s) => {
+                update_partial_notif(&mut partial_notif, &s);
+            },
+            Err(e) => {
+                match e {
+                    TryRecvError::Empty => {},
+                    TryRecvError::Disconnected => {eprintln!("Error: Partial transcript channel unexpectedly closed");}
+                }
+            }
+        }
         match speechrx.try_recv() {
             Ok(s) => {
                 println!("Heard: \"{}\"", s);
+                // the final result is in, so the partial transcript isn't needed anymore
+                if let Some(handle) = partial_notif.take() {
+                    handle.close();
+                }
                 //let _ = speaktx.send(SpeakMessage::Say(s));
                 match command_director.dispatch_command(s) {
                     DispatchResult::Done => {statetx.send(State::Waiting).unwrap()},
                     DispatchResult::Continue => {statetx.send(State::Listening).unwrap()},
+                    DispatchResult::Working => {}, // the result will come from poll instead
                 }
             },
             Err(e) => {
@@ -105,6 +204,12 @@ fn main() {
                 }
             }
         }
+        // check on any command running in the background
+        match command_director.poll() {
+            Some(DispatchResult::Done) => {statetx.send(State::Waiting).unwrap()},
+            Some(DispatchResult::Continue) => {statetx.send(State::Listening).unwrap()},
+            Some(DispatchResult::Working) | None => {},
+        }
     }
     
     // gotta drop these first so all the inputs to the speaking channel are closed
@@ -151,6 +256,31 @@ fn say(text : String) -> io::Result<u8> {
     return Ok(0);
 }
 
+// plays an earcon through aplay, made as sine waves in the same format piper gives
+fn play_earcon(earcon : &Earcon) -> io::Result<u8> {
+    const RATE : f32 = 22050.0;
+    let mut samples : Vec<u8> = Vec::new();
+    for (frequency, millis) in earcon.notes() {
+        let count = (RATE * *millis as f32 / 1000.0) as usize;
+        for i in 0..count {
+            // fade each note in and out a little, so it doesn't click
+            let fade = (i.min(count - i) as f32 / (RATE * 0.01)).min(1.0);
+            let value = (i as f32 * frequency * std::f32::consts::TAU / RATE).sin() * fade * 0.3 * i16::MAX as f32;
+            samples.extend_from_slice(&(value as i16).to_le_bytes());
+        }
+    }
+
+    let mut child = Command::new("aplay")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .args(["-r","22050","-f","S16_LE","-t","raw"])
+        .spawn()?;
+    child.stdin.as_ref().unwrap().write_all(&samples)?;
+    child.wait()?;
+
+    return Ok(0);
+}
+
 // initializes Rustpotter using settings passed in, plus some baked in ones that I don't expect to change'
 fn rustpotter_init(format : SampleFormat, sample_rate : u16, wwpath : &str) -> Result<Rustpotter, &'static str> {
     let mut rp_config = RustpotterConfig::default();
@@ -190,7 +320,7 @@ fn rustpotter_init(format : SampleFormat, sample_rate : u16, wwpath : &str) -> R
 
 // set up all the audio input and transcription stuff
 fn transcription_init(ack_phrase : String, wwpath : String, vosk_path : String, speaktx : Sender<SpeakMessage>)
-    -> Result<(Stream, Receiver<String>, SyncSender<State>), &'static str> {
+    -> Result<(Stream, Receiver<String>, Receiver<String>, SyncSender<State>), &'static str> {
     // state stuff
     let mut state = State::Waiting;
 
@@ -208,6 +338,10 @@ fn transcription_init(ack_phrase : String, wwpath : String, vosk_path : String,
     // make a channel for sending heard text from the user
     let (texttx, textrx) = mpsc::channel::<String>();
 
+    // make a channel for sending partial transcripts while the user is still talking
+    let (partialtx, partialrx) = mpsc::channel::<String>();
+    let mut last_partial = String::new();
+
     // make a channel for telling the thread to go back to listening
     let (signaltx, signalrx) = mpsc::sync_channel::<State>(0);
 
@@ -229,6 +363,14 @@ fn transcription_init(ack_phrase : String, wwpath : String, vosk_path : String,
         &supported_config.into(),
         move |data: & [i16], _: &cpal::InputCallbackInfo| {
             // react to stream events and read or write stream data here.
+            // the main thread can also switch states while nothing's running, like when it's told to stop listening
+            if !matches!(state, State::CommandRunning) {
+                if let Ok(s) = signalrx.try_recv() {
+                    recog.reset();
+                    last_partial.clear();
+                    state = s;
+                }
+            }
             match state {
                 State::Waiting => {
                     let mut data_vec = data.to_vec().into();
@@ -239,7 +381,7 @@ fn transcription_init(ack_phrase : String, wwpath : String, vosk_path : String,
                         let detection = rp.process_samples(samples_buffer.drain(..rp_buffer_size).collect());
                         if let Some(detection) = detection {
                             println!("Detected: {:?}", detection);
-                            let _ = speaktx.send(SpeakMessage::Say(ack_phrase.clone()));
+                            let _ = speaktx.send(SpeakMessage::Ack(ack_phrase.clone()));
                             state = State::Listening;
                         }
                     }
@@ -250,8 +392,17 @@ fn transcription_init(ack_phrase : String, wwpath : String, vosk_path : String,
                         let vosk::CompleteResult::Single(single_result) = recog.final_result() else { todo!() };
                         let _ = texttx.send(String::from(single_result.text));
                         recog.reset();
+                        last_partial.clear();
                         state = State::CommandRunning;
                     }
+                    if decoding_state == DecodingState::Running {
+                        // only pass along the partial transcript when it actually changes
+                        let partial = recog.partial_result().partial;
+                        if !partial.is_empty() && partial != last_partial {
+                            last_partial = String::from(partial);
+                            let _ = partialtx.send(last_partial.clone());
+                        }
+                    }
                     if decoding_state == DecodingState::Failed {
                         eprintln!("Something broke with decoding the audio in Vosk");
                     }
@@ -283,7 +434,7 @@ fn transcription_init(ack_phrase : String, wwpath : String, vosk_path : String,
     //    Err(e) => { eprintln!("Error making audio input stream: {}", e); }
     //}
     
-    return Ok((in_stream, textrx, signaltx));
+    return Ok((in_stream, textrx, partialrx, signaltx));
 }
 
 // initialize the tray menu
@@ -302,6 +453,14 @@ fn tray_menu_init() -> Receiver<TrayMessage> {
     let mut tray = TrayItem::new("Zinnia", icon).unwrap();
     tray.add_label("ZINNIA").unwrap();
     let (tray_tx, tray_rx) = mpsc::sync_channel::<TrayMessage>(2);
+    let tray_offline_tx = tray_tx.clone();
+    tray.add_menu_item("Toggle Offline Mode", move || {
+        tray_offline_tx.send(TrayMessage::ToggleOffline).unwrap();
+    }).unwrap();
+    let tray_stop_tx = tray_tx.clone();
+    tray.add_menu_item("Stop Listening", move || {
+        tray_stop_tx.send(TrayMessage::StopListening).unwrap();
+    }).unwrap();
     let tray_quit_tx = tray_tx.clone();
     let id_menu = tray.inner_mut()
         .add_menu_item_with_id("Close Zinnia", move || {
@@ -312,13 +471,40 @@ fn tray_menu_init() -> Receiver<TrayMessage> {
 }
 
 // sends a system notification with the given text
-fn send_notif(text : &String) {
+// a lasting notification stays up until it's dismissed, otherwise it goes away after long enough to read it
+fn send_notif(text : &String, lasting : bool) {
+    let timeout = if lasting {
+        Timeout::Never
+    } else {
+        Timeout::Milliseconds((3000 as f32 * (text.len() as f32 / 20.0)) as u32)
+    };
     Notification::new()
         .summary("ZINNIA")
         .appname("ZINNIA")
         .body(text)
         .icon(fs::canonicalize("resources/1f444.png").unwrap().to_str().unwrap())
-        .timeout(Timeout::Milliseconds((3000 as f32 * (text.len() as f32 / 20.0)) as u32))
+        .timeout(timeout)
         .show().unwrap();
 }
 
+
+// shows the partial transcript in a notification, updating the existing one if there is one
+fn update_partial_notif(handle : &mut Option<NotificationHandle>, text : &String) {
+    match handle {
+        Some(h) => {
+            h.body(&format!("Hearing: {}", text));
+            h.update();
+        },
+        None => {
+            match Notification::new()
+                .summary("ZINNIA")
+                .appname("ZINNIA")
+                .body(&format!("Hearing: {}", text))
+                .timeout(Timeout::Never)
+                .show() {
+                Ok(h) => {*handle = Some(h)},
+                Err(e) => {eprintln!("Error showing partial transcript: {}", e);}
+            }
+        }
+    }
+}
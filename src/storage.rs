@@ -0,0 +1,63 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use json;
+use json::JsonValue;
+
+// a folder of JSON files for anything that needs to be remembered between runs
+// cloning it is cheap, and all the clones use the same folder
+#[derive(Clone)]
+pub struct Storage {
+    dir : PathBuf,
+}
+impl Storage {
+    pub fn new(dir : &str) -> Storage {
+        return Storage{dir : PathBuf::from(dir)};
+    }
+
+    // where a file with the given name is kept
+    pub fn path(&self, name : &str) -> PathBuf {
+        return self.dir.join(name);
+    }
+
+    // reads a JSON file, giving back None if it doesn't exist or can't be read
+    pub fn load(&self, name : &str) -> Option<JsonValue> {
+        let contents = fs::read_to_string(self.path(name)).ok()?;
+        match json::parse(&contents) {
+            Ok(v) => {return Some(v)},
+            Err(e) => {
+                eprintln!("Error reading {}, ignoring it: {}", self.path(name).display(), e);
+                return None;
+            }
+        }
+    }
+
+    // writes a JSON file, replacing it all at once so a crash partway through can't leave half a file
+    pub fn save(&self, name : &str, value : &JsonValue) -> Result<(), String> {
+        return self.save_text(name, &value.pretty(2));
+    }
+
+    // writes any other kind of file the same way, like a list exported for reading elsewhere
+    pub fn save_text(&self, name : &str, contents : &str) -> Result<(), String> {
+        let path = self.path(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, contents).map_err(|e| e.to_string())?;
+        fs::rename(&temp, &path).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    // adds to the end of a file, making it if it isn't there yet
+    // unlike saving, this happens in place, since a crash can only lose what was being added
+    pub fn append_text(&self, name : &str, contents : &str) -> Result<(), String> {
+        let path = self.path(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+        file.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::{Earcon, SpeakMessage};
+use chrono::{DateTime, Local};
+
+// how often running timers are checked to see if they've finished
+const TICK : Duration = Duration::from_millis(250);
+
+pub enum TimerKind {
+    Countdown {
+        length : Duration,
+        message : String, // what to say when it runs out
+        lasting : bool, // whether it's a reminder, shown until it's dismissed
+        // when a reminder is due, which it's checked against instead of the length
+        // unlike an Instant, this keeps counting while the computer is asleep and follows clock changes
+        due : Option<DateTime<Local>>,
+    },
+    Stopwatch {
+        laps : Vec<Duration>, // the total time at each lap
+    },
+    Routine {
+        steps : Vec<Step>,
+        step : usize, // index into steps
+        finish : String, // what to say when the last step is done
+    },
+}
+
+// one part of a routine, like a round of work or a break
+pub struct Step {
+    pub name : String,
+    pub round : usize,
+    pub length : Duration,
+    pub message : String, // what to say when it starts
+}
+
+// a timer or stopwatch that's being kept track of
+pub struct Timer {
+    pub name : String, // empty if it wasn't given one
+    pub kind : TimerKind,
+    started : Instant,
+    paused : Option<Instant>,
+    paused_for : Duration, // time spent paused, which doesn't count as elapsed
+    then : Option<Box<dyn FnOnce() + Send>>, // run once a countdown has run out and been announced
+}
+impl Timer {
+    fn with_kind(name : &str, kind : TimerKind) -> Timer {
+        return Timer{name : String::from(name), kind, started : Instant::now(), paused : None, paused_for : Duration::ZERO,
+            then : None};
+    }
+
+    pub fn countdown(name : &str, length : Duration, message : &str) -> Timer {
+        return Timer::with_kind(name, TimerKind::Countdown{length, message : String::from(message), lasting : false, due : None});
+    }
+
+    pub fn reminder(name : &str, due : DateTime<Local>, message : &str) -> Timer {
+        return Timer::with_kind(name, TimerKind::Countdown{length : Duration::ZERO, message : String::from(message), lasting : true,
+            due : Some(due)});
+    }
+
+    pub fn stopwatch(name : &str) -> Timer {
+        return Timer::with_kind(name, TimerKind::Stopwatch{laps : Vec::new()});
+    }
+
+    pub fn routine(name : &str, steps : Vec<Step>, finish : &str) -> Timer {
+        return Timer::with_kind(name, TimerKind::Routine{steps, step : 0, finish : String::from(finish)});
+    }
+
+    // gives something to do once the countdown runs out, like forgetting a saved reminder
+    pub fn then(mut self, job : impl FnOnce() + Send + 'static) -> Timer {
+        self.then = Some(Box::new(job));
+        return self;
+    }
+
+    pub fn is_reminder(&self) -> bool {
+        return matches!(self.kind, TimerKind::Countdown{lasting : true, ..});
+    }
+
+    // time spent running, since it started or since a routine's current step started
+    pub fn elapsed(&self) -> Duration {
+        let until = self.paused.unwrap_or_else(Instant::now);
+        return until.duration_since(self.started).saturating_sub(self.paused_for);
+    }
+
+    // how long until a countdown runs out or a routine's step is over, or None for a stopwatch
+    pub fn remaining(&self) -> Option<Duration> {
+        match &self.kind {
+            TimerKind::Countdown{due : Some(due), ..} => {return Some((*due - Local::now()).to_std().unwrap_or(Duration::ZERO))},
+            TimerKind::Countdown{length, ..} => {return Some(length.saturating_sub(self.elapsed()))},
+            TimerKind::Stopwatch{..} => {return None},
+            TimerKind::Routine{steps, step, ..} => {return Some(steps[*step].length.saturating_sub(self.elapsed()))},
+        }
+    }
+
+    pub fn is_stopwatch(&self) -> bool {
+        return matches!(self.kind, TimerKind::Stopwatch{..});
+    }
+
+    pub fn is_routine(&self) -> bool {
+        return matches!(self.kind, TimerKind::Routine{..});
+    }
+
+    pub fn is_paused(&self) -> bool {
+        return self.paused.is_some();
+    }
+
+    // gives back false if it was already paused
+    pub fn pause(&mut self) -> bool {
+        if self.paused.is_some() {
+            return false;
+        }
+        self.paused = Some(Instant::now());
+        return true;
+    }
+
+    // gives back false if it wasn't paused
+    pub fn resume(&mut self) -> bool {
+        match self.paused.take() {
+            Some(since) => {
+                self.paused_for += since.elapsed();
+                return true;
+            },
+            None => {return false},
+        }
+    }
+
+    // moves a routine on to its next step, giving back what to play and say about it
+    // the bool is false once there are no steps left
+    pub fn advance(&mut self) -> (bool, Vec<SpeakMessage>) {
+        if let TimerKind::Routine{steps, step, finish} = &mut self.kind {
+            *step += 1;
+            self.started = Instant::now();
+            self.paused_for = Duration::ZERO;
+            if self.paused.is_some() {
+                self.paused = Some(self.started);
+            }
+            if *step >= steps.len() {
+                return (false, vec![SpeakMessage::Earcon(Earcon::Finish), SpeakMessage::Say(finish.clone())]);
+            }
+            return (true, vec![SpeakMessage::Earcon(Earcon::Switch), SpeakMessage::Say(steps[*step].message.clone())]);
+        }
+        return (false, Vec::new());
+    }
+}
+
+// keeps track of every running timer and stopwatch, with one thread to announce the ones that finish
+#[derive(Clone)]
+pub struct TimerService {
+    timers : Arc<Mutex<Vec<Timer>>>,
+}
+impl TimerService {
+    pub fn new(speak : Sender<SpeakMessage>) -> TimerService {
+        let timers = Arc::new(Mutex::new(Vec::<Timer>::new()));
+        let watched = timers.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(TICK);
+                let mut announcements = Vec::new();
+                let mut jobs = Vec::new();
+                {
+                    let mut timers = watched.lock().unwrap_or_else(|e| e.into_inner());
+                    let mut index = 0;
+                    while index < timers.len() {
+                        if timers[index].remaining() != Some(Duration::ZERO) {
+                            index += 1;
+                            continue;
+                        }
+                        if timers[index].is_routine() {
+                            let (running, said) = timers[index].advance();
+                            announcements.extend(said);
+                            if running {
+                                index += 1;
+                                continue;
+                            }
+                        } else if let TimerKind::Countdown{message, lasting, ..} = &timers[index].kind {
+                            if *lasting {
+                                announcements.push(SpeakMessage::Remind(message.clone()));
+                            } else {
+                                announcements.push(SpeakMessage::Say(message.clone()));
+                            }
+                        }
+                        jobs.extend(timers.remove(index).then);
+                    }
+                }
+                for message in announcements {
+                    if speak.send(message).is_err() {
+                        return; // nothing is listening anymore
+                    }
+                }
+                for job in jobs {
+                    job();
+                }
+            }
+        });
+        return TimerService{timers};
+    }
+
+    // gives access to the timers while they're locked, for anything that needs to look at or change them
+    pub fn lock(&self) -> MutexGuard<'_, Vec<Timer>> {
+        return self.timers.lock().unwrap_or_else(|e| e.into_inner());
+    }
+
+    pub fn start(&self, timer : Timer) {
+        self.lock().push(timer);
+    }
+
+    // takes a timer out, stopping it
+    pub fn remove(&self, name : &str, stopwatch : bool) -> Option<Timer> {
+        let mut timers = self.lock();
+        let index = timers.iter().position(|t| t.name == name && t.is_stopwatch() == stopwatch)?;
+        return Some(timers.remove(index));
+    }
+
+    // the names of the running stopwatches, or the running countdowns
+    pub fn names(&self, stopwatch : bool) -> Vec<String> {
+        return self.lock().iter().filter(|t| t.is_stopwatch() == stopwatch).map(|t| t.name.clone()).collect();
+    }
+}
+
+// a length of time the way it would be said, like "1 hour, 4 minutes, and 10 seconds"
+// short times get a tenth of a second, since those matter more for a stopwatch
+pub fn spoken_duration(duration : Duration) -> String {
+    if duration < Duration::from_secs(10) {
+        let seconds = (duration.as_secs_f64() * 10.0).round() / 10.0;
+        return format!("{} second{}", seconds, if seconds == 1.0 {""} else {"s"});
+    }
+    let total = duration.as_secs();
+    let mut parts = Vec::new();
+    for (amount, unit) in [(total / 3600, "hour"), (total / 60 % 60, "minute"), (total % 60, "second")] {
+        if amount > 0 {
+            parts.push(format!("{} {}{}", amount, unit, if amount == 1 {""} else {"s"}));
+        }
+    }
+    return match parts.len() {
+        1 => {parts.remove(0)},
+        2 => {parts.join(" and ")},
+        _ => {format!("{}, {}, and {}", parts[0], parts[1], parts[2])},
+    };
+}
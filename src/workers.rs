@@ -0,0 +1,48 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+use std::panic::{self, AssertUnwindSafe};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// a fixed set of threads for running work that would otherwise hold up the main loop
+pub struct WorkerPool {
+    jobs : Sender<Job>,
+}
+impl WorkerPool {
+    pub fn new(size : usize) -> WorkerPool {
+        let (jobs, jobsrx) = mpsc::channel::<Job>();
+        // the workers take turns pulling jobs off the one channel
+        let jobsrx = Arc::new(Mutex::new(jobsrx));
+        for _ in 0..size.max(1) {
+            let jobsrx = jobsrx.clone();
+            thread::spawn(move || {
+                loop {
+                    let job = match WorkerPool::next_job(&jobsrx) {
+                        Some(j) => {j},
+                        None => {break}, // the pool was dropped
+                    };
+                    // a job that panics only loses its own work, the worker goes on to the next one
+                    // anything waiting on the job's result sees its channel close and can deal with it
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        eprintln!("Error: a job on a worker thread panicked");
+                    }
+                }
+            });
+        }
+        return WorkerPool{jobs};
+    }
+
+    // hands a job to whichever worker is free next
+    pub fn execute(&self, job : impl FnOnce() + Send + 'static) {
+        if self.jobs.send(Box::new(job)).is_err() {
+            eprintln!("Error: all worker threads have stopped");
+        }
+    }
+
+    fn next_job(jobsrx : &Arc<Mutex<Receiver<Job>>>) -> Option<Job> {
+        // the lock is only held while waiting, not while the job runs
+        let jobsrx = jobsrx.lock().ok()?;
+        return jobsrx.recv().ok();
+    }
+}